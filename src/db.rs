@@ -1,5 +1,6 @@
 use rusqlite::{Connection, Result};
-use std::path::PathBuf;
+use std::{collections::HashMap, path::PathBuf};
+use time::OffsetDateTime;
 
 /*
  * table data {
@@ -9,6 +10,41 @@ use std::path::PathBuf;
  * table notifications {
  *    id TEXT PRIMARY KEY,
  * }
+ * table threads {
+ *    webhook_id TEXT,
+ *    thread_key TEXT,
+ *    thread_id TEXT,
+ *    PRIMARY KEY (webhook_id, thread_key)
+ * }
+ * table deliveries {
+ *    notification_id TEXT,
+ *    output TEXT,
+ *    PRIMARY KEY (notification_id, output)
+ * }
+ * table digest_entries {
+ *    id INTEGER PRIMARY KEY AUTOINCREMENT,
+ *    output TEXT,
+ *    recipient TEXT,
+ *    created_at INTEGER,
+ *    data BLOB
+ * }
+ * table seen_ids {
+ *    profile TEXT,
+ *    kind TEXT,
+ *    id TEXT,
+ *    topic TEXT,
+ *    shared_by TEXT,
+ *    seen_count INTEGER,
+ *    last_seen INTEGER,
+ *    PRIMARY KEY (profile, kind, id)
+ * }
+ * table mappings {
+ *    profile TEXT,
+ *    kind TEXT,
+ *    id TEXT,
+ *    name TEXT,
+ *    PRIMARY KEY (profile, kind, id)
+ * }
  */
 
 pub fn connect(db_path: PathBuf) -> Result<Connection> {
@@ -29,9 +65,99 @@ pub fn connect(db_path: PathBuf) -> Result<Connection> {
         [],
     )?;
 
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS threads (
+            webhook_id TEXT,
+            thread_key TEXT,
+            thread_id TEXT,
+            PRIMARY KEY (webhook_id, thread_key)
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS deliveries (
+            notification_id TEXT,
+            output TEXT,
+            PRIMARY KEY (notification_id, output)
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS digest_entries (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            output TEXT,
+            recipient TEXT,
+            created_at INTEGER,
+            data BLOB
+        )",
+        [],
+    )?;
+
+    create_profile_table(
+        &conn,
+        "seen_ids",
+        "profile TEXT,
+            kind TEXT,
+            id TEXT,
+            topic TEXT,
+            shared_by TEXT,
+            seen_count INTEGER,
+            last_seen INTEGER,
+            PRIMARY KEY (profile, kind, id)",
+        "kind, id, topic, shared_by, seen_count, last_seen",
+    )?;
+
+    create_profile_table(
+        &conn,
+        "mappings",
+        "profile TEXT,
+            kind TEXT,
+            id TEXT,
+            name TEXT,
+            PRIMARY KEY (profile, kind, id)",
+        "kind, id, name",
+    )?;
+
     Ok(conn)
 }
 
+// Tables created before profiles existed have no `profile` column and a primary key without it,
+// so they are rebuilt with the existing rows moved to the main section
+fn create_profile_table(
+    conn: &Connection,
+    table: &str,
+    columns: &str,
+    old_columns: &str,
+) -> Result<()> {
+    let has_table: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?)",
+        [table],
+        |row| row.get(0),
+    )?;
+    let has_profile: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM pragma_table_info(?) WHERE name = 'profile')",
+        [table],
+        |row| row.get(0),
+    )?;
+
+    if !has_table {
+        conn.execute(&format!("CREATE TABLE {} ({})", table, columns), [])?;
+    } else if !has_profile {
+        conn.execute_batch(&format!(
+            "BEGIN;
+            ALTER TABLE {table} RENAME TO {table}_old;
+            CREATE TABLE {table} ({columns});
+            INSERT INTO {table} (profile, {old_columns}) SELECT '', {old_columns} FROM {table}_old;
+            DROP TABLE {table}_old;
+            COMMIT;"
+        ))?;
+    }
+
+    Ok(())
+}
+
 pub fn get_data<T>(conn: &Connection, id: &str) -> Result<Option<T>>
 where
     T: serde::de::DeserializeOwned,
@@ -48,9 +174,9 @@ where
     }
 }
 
-pub fn set_data<T: ?Sized>(conn: &Connection, id: &str, data: &T) -> Result<()>
+pub fn set_data<T>(conn: &Connection, id: &str, data: &T) -> Result<()>
 where
-    T: serde::Serialize,
+    T: serde::Serialize + ?Sized,
 {
     let data = bincode::serialize(data).unwrap();
     conn.execute(
@@ -104,3 +230,241 @@ pub fn add_notification(conn: &Connection, id: &str) -> Result<()> {
 
     Ok(())
 }
+
+pub fn get_thread(conn: &Connection, webhook_id: &str, thread_key: &str) -> Result<Option<String>> {
+    let mut stmt =
+        conn.prepare("SELECT thread_id FROM threads WHERE webhook_id = ? AND thread_key = ?")?;
+    let mut rows = stmt.query([webhook_id, thread_key])?;
+
+    if let Some(row) = rows.next()? {
+        let thread_id: String = row.get(0)?;
+        Ok(Some(thread_id))
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn set_thread(
+    conn: &Connection,
+    webhook_id: &str,
+    thread_key: &str,
+    thread_id: &str,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO threads (webhook_id, thread_key, thread_id) VALUES (?, ?, ?) ON CONFLICT (webhook_id, thread_key) DO UPDATE SET thread_id = ?",
+        [webhook_id, thread_key, thread_id, thread_id],
+    )?;
+
+    Ok(())
+}
+
+pub fn remove_thread(conn: &Connection, webhook_id: &str, thread_key: &str) -> Result<()> {
+    conn.execute(
+        "DELETE FROM threads WHERE webhook_id = ? AND thread_key = ?",
+        [webhook_id, thread_key],
+    )?;
+
+    Ok(())
+}
+
+pub fn is_delivered(conn: &Connection, notification_id: &str, output: &str) -> Result<bool> {
+    let mut stmt =
+        conn.prepare("SELECT 1 FROM deliveries WHERE notification_id = ? AND output = ?")?;
+    let mut rows = stmt.query([notification_id, output])?;
+
+    Ok(rows.next()?.is_some())
+}
+
+pub fn add_delivery(conn: &Connection, notification_id: &str, output: &str) -> Result<()> {
+    conn.execute(
+        "INSERT INTO deliveries (notification_id, output) VALUES (?, ?) ON CONFLICT (notification_id, output) DO NOTHING",
+        [notification_id, output],
+    )?;
+
+    Ok(())
+}
+
+pub fn remove_deliveries(conn: &Connection, notification_id: &str) -> Result<()> {
+    conn.execute(
+        "DELETE FROM deliveries WHERE notification_id = ?",
+        [notification_id],
+    )?;
+
+    Ok(())
+}
+
+pub struct DigestEntry<T> {
+    pub id: i64,
+    pub recipient: String,
+    pub created_at: i64,
+    pub data: T,
+}
+
+pub fn add_digest_entry<T>(conn: &Connection, output: &str, recipient: &str, data: &T) -> Result<()>
+where
+    T: serde::Serialize,
+{
+    let data = bincode::serialize(data).unwrap();
+    conn.execute(
+        "INSERT INTO digest_entries (output, recipient, created_at, data) VALUES (?, ?, ?, ?)",
+        rusqlite::params![
+            output,
+            recipient,
+            OffsetDateTime::now_utc().unix_timestamp(),
+            data
+        ],
+    )?;
+
+    Ok(())
+}
+
+pub fn get_digest_entries<T>(conn: &Connection, output: &str) -> Result<Vec<DigestEntry<T>>>
+where
+    T: serde::de::DeserializeOwned,
+{
+    let mut stmt = conn.prepare(
+        "SELECT id, recipient, created_at, data FROM digest_entries WHERE output = ? ORDER BY id",
+    )?;
+    let mut rows = stmt.query([output])?;
+
+    let mut entries = Vec::new();
+
+    while let Some(row) = rows.next()? {
+        let data: Vec<u8> = row.get(3)?;
+        entries.push(DigestEntry {
+            id: row.get(0)?,
+            recipient: row.get(1)?,
+            created_at: row.get(2)?,
+            data: bincode::deserialize(&data).unwrap(),
+        });
+    }
+
+    Ok(entries)
+}
+
+pub fn remove_digest_entries(conn: &Connection, ids: &[i64]) -> Result<()> {
+    for id in ids {
+        conn.execute("DELETE FROM digest_entries WHERE id = ?", [id])?;
+    }
+
+    Ok(())
+}
+
+// Keeps the topic and sharer of the latest event that used the ID, to help with finding out what it is
+pub fn add_seen_id(
+    conn: &Connection,
+    profile: &str,
+    kind: &str,
+    id: &str,
+    topic: &str,
+    shared_by: &str,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO seen_ids (profile, kind, id, topic, shared_by, seen_count, last_seen) VALUES (?1, ?2, ?3, ?4, ?5, 1, ?6)
+            ON CONFLICT (profile, kind, id) DO UPDATE SET topic = ?4, shared_by = ?5, seen_count = seen_count + 1, last_seen = ?6",
+        rusqlite::params![
+            profile,
+            kind,
+            id,
+            topic,
+            shared_by,
+            OffsetDateTime::now_utc().unix_timestamp()
+        ],
+    )?;
+
+    Ok(())
+}
+
+pub struct SeenId {
+    pub profile: String,
+    pub kind: String,
+    pub id: String,
+    pub topic: String,
+    pub shared_by: String,
+    pub seen_count: i64,
+    pub last_seen: i64,
+}
+
+pub fn get_seen_ids(conn: &Connection) -> Result<Vec<SeenId>> {
+    let mut stmt = conn.prepare(
+        "SELECT profile, kind, id, topic, shared_by, seen_count, last_seen FROM seen_ids ORDER BY profile, kind, id",
+    )?;
+    let mut rows = stmt.query([])?;
+
+    let mut seen_ids = Vec::new();
+
+    while let Some(row) = rows.next()? {
+        seen_ids.push(SeenId {
+            profile: row.get(0)?,
+            kind: row.get(1)?,
+            id: row.get(2)?,
+            topic: row.get(3)?,
+            shared_by: row.get(4)?,
+            seen_count: row.get(5)?,
+            last_seen: row.get(6)?,
+        });
+    }
+
+    Ok(seen_ids)
+}
+
+pub fn get_mappings(
+    conn: &Connection,
+    profile: &str,
+    kind: &str,
+) -> Result<HashMap<String, String>> {
+    let mut stmt = conn.prepare("SELECT id, name FROM mappings WHERE profile = ? AND kind = ?")?;
+    let mut rows = stmt.query([profile, kind])?;
+
+    let mut mappings = HashMap::new();
+
+    while let Some(row) = rows.next()? {
+        mappings.insert(row.get(0)?, row.get(1)?);
+    }
+
+    Ok(mappings)
+}
+
+pub fn set_mapping(
+    conn: &Connection,
+    profile: &str,
+    kind: &str,
+    id: &str,
+    name: &str,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO mappings (profile, kind, id, name) VALUES (?1, ?2, ?3, ?4) ON CONFLICT (profile, kind, id) DO UPDATE SET name = ?4",
+        [profile, kind, id, name],
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_old_mappings_to_main_section() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE mappings (kind TEXT, id TEXT, name TEXT, PRIMARY KEY (kind, id));
+            INSERT INTO mappings (kind, id, name) VALUES ('subject', '1', 'Matematyka');",
+        )
+        .unwrap();
+
+        create_profile_table(
+            &conn,
+            "mappings",
+            "profile TEXT, kind TEXT, id TEXT, name TEXT, PRIMARY KEY (profile, kind, id)",
+            "kind, id, name",
+        )
+        .unwrap();
+
+        let mappings = get_mappings(&conn, "", "subject").unwrap();
+        assert_eq!(mappings.get("1").map(String::as_str), Some("Matematyka"));
+
+        set_mapping(&conn, "vulcan", "subject", "1", "Fizyka").unwrap();
+        assert_eq!(get_mappings(&conn, "vulcan", "subject").unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,85 @@
+use serde::Deserialize;
+
+use crate::{profiles::ProfileConfig, LibrusConfig};
+
+// Event types built into Szkolny.eu, with the colours the app uses for them
+const EVENT_TYPES: &[(i32, &str, &str, u32)] = &[
+    (-1, "Zadanie domowe", "📝", 0x795548),
+    (0, "Wydarzenie", "📅", 0xffc107),
+    (1, "Sprawdzian", "📚", 0xf44336),
+    (2, "Kartkówka", "✏️", 0x76ff03),
+    (3, "Wypracowanie", "🖊️", 0x4caf50),
+    (4, "Projekt", "🧩", 0x673ab7),
+    (5, "Zebranie z rodzicami", "👪", 0x90caf9),
+    (6, "Wycieczka", "🚌", 0x4caf50),
+    (7, "Lektura", "📖", 0xffeb3b),
+    (8, "Wydarzenie klasowe", "🎉", 0x388e3c),
+    (9, "Informacja", "ℹ️", 0x039be5),
+];
+
+const UNKNOWN_EMOJI: &str = "❔";
+
+// Overrides for a single event type, unset fields keep the built-in values
+#[derive(Deserialize)]
+pub struct EventTypeConfig {
+    name: Option<String>,
+    emoji: Option<String>,
+    color: Option<u32>,
+}
+
+pub struct EventType {
+    pub name: String,
+    pub emoji: String,
+    pub color: Option<u32>,
+}
+
+impl EventType {
+    pub fn label(&self) -> String {
+        format!("{} {}", self.emoji, self.name)
+    }
+}
+
+impl EventTypeConfig {
+    fn apply(&self, event_type: &mut EventType) {
+        if let Some(name) = &self.name {
+            event_type.name = name.clone();
+        }
+        if let Some(emoji) = &self.emoji {
+            event_type.emoji = emoji.clone();
+        }
+        if let Some(color) = self.color {
+            event_type.color = Some(color);
+        }
+    }
+}
+
+// Overrides from the profile are applied on top of the ones from the main section
+pub fn event_type(
+    id: i32,
+    librus_config: &LibrusConfig,
+    profile: Option<&ProfileConfig>,
+) -> EventType {
+    let mut event_type = match EVENT_TYPES.iter().find(|(type_id, ..)| *type_id == id) {
+        Some((_, name, emoji, color)) => EventType {
+            name: name.to_string(),
+            emoji: emoji.to_string(),
+            color: Some(*color),
+        },
+        None => EventType {
+            name: format!("Typ {}", id),
+            emoji: UNKNOWN_EMOJI.to_owned(),
+            color: None,
+        },
+    };
+
+    let id = id.to_string();
+
+    if let Some(overrides) = librus_config.event_types.get(&id) {
+        overrides.apply(&mut event_type);
+    }
+    if let Some(overrides) = profile.and_then(|profile| profile.event_types.get(&id)) {
+        overrides.apply(&mut event_type);
+    }
+
+    event_type
+}
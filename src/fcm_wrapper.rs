@@ -37,7 +37,7 @@ impl FcmMessageStream {
 
         tokio::spawn(async move {
             loop {
-                println!("  -> Connecting to FCM...");
+                log!("  -> Connecting to FCM...");
                 while let Err(e) = listener.connect().await {
                     eprintln!("Failed to connect to FCM: {}", e);
                     tokio::time::sleep(std::time::Duration::from_secs(5)).await;
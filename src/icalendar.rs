@@ -0,0 +1,237 @@
+use time::{format_description::FormatItem, macros::format_description, Duration, OffsetDateTime};
+
+const PRODID: &str = concat!(
+    "-//",
+    env!("CARGO_PKG_NAME"),
+    "//",
+    env!("CARGO_PKG_VERSION"),
+    "//PL"
+);
+const UID_DOMAIN: &str = "szkolny.eu";
+const LESSON_DURATION: Duration = Duration::minutes(45);
+const MAX_LINE_LENGTH: usize = 75;
+
+const DATE_FORMAT: &[FormatItem] = format_description!("[year][month][day]");
+const DATETIME_FORMAT: &[FormatItem] =
+    format_description!("[year][month][day]T[hour][minute][second]Z");
+
+pub enum CalendarEvent {
+    Publish {
+        event_id: String,
+        start: OffsetDateTime,
+        all_day: bool,
+        summary: String,
+        description: String,
+        categories: Option<String>,
+    },
+    Cancel {
+        event_id: String,
+    },
+}
+
+impl CalendarEvent {
+    pub fn event_id(&self) -> &str {
+        match self {
+            CalendarEvent::Publish { event_id, .. } | CalendarEvent::Cancel { event_id } => {
+                event_id
+            }
+        }
+    }
+
+    pub fn file_name(&self) -> String {
+        format!("event-{}.ics", self.event_id())
+    }
+
+    pub fn to_ics(&self) -> String {
+        let now = OffsetDateTime::now_utc();
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_owned(),
+            "VERSION:2.0".to_owned(),
+            format!("PRODID:{}", PRODID),
+        ];
+
+        match self {
+            CalendarEvent::Publish {
+                event_id,
+                start,
+                all_day,
+                summary,
+                description,
+                categories,
+            } => {
+                lines.push("METHOD:PUBLISH".to_owned());
+                lines.push("BEGIN:VEVENT".to_owned());
+                lines.push(format!("UID:{}", uid(event_id)));
+                lines.push(format!("DTSTAMP:{}", format_datetime(now)));
+
+                if *all_day {
+                    lines.push(format!("DTSTART;VALUE=DATE:{}", format_date(*start)));
+                    lines.push(format!(
+                        "DTEND;VALUE=DATE:{}",
+                        format_date(*start + Duration::days(1))
+                    ));
+                } else {
+                    lines.push(format!("DTSTART:{}", format_datetime(*start)));
+                    lines.push(format!(
+                        "DTEND:{}",
+                        format_datetime(*start + LESSON_DURATION)
+                    ));
+                }
+
+                lines.push(format!("SUMMARY:{}", escape_text(summary)));
+                lines.push(format!("DESCRIPTION:{}", escape_text(description)));
+                if let Some(categories) = categories {
+                    lines.push(format!("CATEGORIES:{}", escape_text(categories)));
+                }
+                lines.push("END:VEVENT".to_owned());
+            }
+            CalendarEvent::Cancel { event_id } => {
+                lines.push("METHOD:CANCEL".to_owned());
+                lines.push("BEGIN:VEVENT".to_owned());
+                lines.push(format!("UID:{}", uid(event_id)));
+                lines.push(format!("DTSTAMP:{}", format_datetime(now)));
+                lines.push("SEQUENCE:1".to_owned());
+                lines.push("STATUS:CANCELLED".to_owned());
+                lines.push("END:VEVENT".to_owned());
+            }
+        }
+
+        lines.push("END:VCALENDAR".to_owned());
+
+        lines
+            .iter()
+            .map(|line| fold_line(line))
+            .collect::<Vec<_>>()
+            .join("\r\n")
+            + "\r\n"
+    }
+}
+
+fn uid(event_id: &str) -> String {
+    format!("event-{}@{}", event_id, UID_DOMAIN)
+}
+
+fn format_date(time: OffsetDateTime) -> String {
+    time.date().format(DATE_FORMAT).unwrap()
+}
+
+fn format_datetime(time: OffsetDateTime) -> String {
+    time.to_offset(time::UtcOffset::UTC)
+        .format(DATETIME_FORMAT)
+        .unwrap()
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+// RFC 5545 limits content lines to 75 octets, longer ones continue on lines starting with a space
+fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut line_length = 0;
+
+    for c in line.chars() {
+        if line_length + c.len_utf8() > MAX_LINE_LENGTH {
+            folded.push_str("\r\n ");
+            line_length = 1;
+        }
+        folded.push(c);
+        line_length += c.len_utf8();
+    }
+
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::datetime;
+
+    use super::*;
+
+    fn unfold(ics: &str) -> String {
+        ics.replace("\r\n ", "")
+    }
+
+    #[test]
+    fn keeps_short_lines() {
+        let line = "a".repeat(MAX_LINE_LENGTH);
+        assert_eq!(fold_line(&line), line);
+    }
+
+    #[test]
+    fn folds_long_lines() {
+        let line = "a".repeat(MAX_LINE_LENGTH + 10);
+        let folded = fold_line(&line);
+
+        let lines: Vec<_> = folded.split("\r\n").collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].len(), MAX_LINE_LENGTH);
+        assert_eq!(lines[1], format!(" {}", "a".repeat(10)));
+    }
+
+    #[test]
+    fn folds_between_characters() {
+        let line = "ą".repeat(MAX_LINE_LENGTH);
+        let folded = fold_line(&line);
+
+        assert!(folded
+            .split("\r\n")
+            .all(|line| line.len() <= MAX_LINE_LENGTH));
+        assert_eq!(unfold(&folded), line);
+    }
+
+    #[test]
+    fn publishes_timed_event() {
+        let event = CalendarEvent::Publish {
+            event_id: "123".to_owned(),
+            start: datetime!(2024-03-04 8:00 UTC),
+            all_day: false,
+            summary: "Sprawdzian, matematyka".to_owned(),
+            description: "Rozdział 1; funkcje\nstrony 10-20".to_owned(),
+            categories: Some("Sprawdzian".to_owned()),
+        };
+        let ics = unfold(&event.to_ics());
+
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert!(ics.contains("\r\nMETHOD:PUBLISH\r\n"));
+        assert!(ics.contains("\r\nUID:event-123@szkolny.eu\r\n"));
+        assert!(ics.contains("\r\nDTSTART:20240304T080000Z\r\n"));
+        assert!(ics.contains("\r\nDTEND:20240304T084500Z\r\n"));
+        assert!(ics.contains("\r\nSUMMARY:Sprawdzian\\, matematyka\r\n"));
+        assert!(ics.contains("\r\nDESCRIPTION:Rozdział 1\\; funkcje\\nstrony 10-20\r\n"));
+        assert!(ics.contains("\r\nCATEGORIES:Sprawdzian\r\n"));
+    }
+
+    #[test]
+    fn publishes_all_day_event() {
+        let event = CalendarEvent::Publish {
+            event_id: "123".to_owned(),
+            start: datetime!(2024-03-04 0:00 UTC),
+            all_day: true,
+            summary: "Wycieczka".to_owned(),
+            description: String::new(),
+            categories: None,
+        };
+        let ics = event.to_ics();
+
+        assert!(ics.contains("\r\nDTSTART;VALUE=DATE:20240304\r\n"));
+        assert!(ics.contains("\r\nDTEND;VALUE=DATE:20240305\r\n"));
+        assert!(!ics.contains("CATEGORIES"));
+    }
+
+    #[test]
+    fn cancels_event() {
+        let event = CalendarEvent::Cancel {
+            event_id: "123".to_owned(),
+        };
+        let ics = event.to_ics();
+
+        assert!(ics.contains("\r\nMETHOD:CANCEL\r\n"));
+        assert!(ics.contains("\r\nUID:event-123@szkolny.eu\r\n"));
+        assert!(ics.contains("\r\nSTATUS:CANCELLED\r\n"));
+    }
+}
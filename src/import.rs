@@ -0,0 +1,333 @@
+use std::{collections::BTreeMap, error::Error, fs, path::Path};
+
+use serde::Deserialize;
+use toml_edit::{Document, Item, TableLike};
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum NameFormat {
+    // Jan Kowalski
+    Full,
+    // Kowalski Jan
+    SurnameFirst,
+    // J. Kowalski
+    Initials,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LibrusSubject {
+    id: i64,
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LibrusUser {
+    id: i64,
+    first_name: String,
+    last_name: String,
+    // Users also contains students and parents, only employees can be teachers
+    #[serde(default = "default_is_employee")]
+    is_employee: bool,
+}
+
+fn default_is_employee() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SzkolnySubject {
+    subject_id: i64,
+    subject_long_name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SzkolnyTeacher {
+    teacher_id: i64,
+    teacher_name: String,
+    teacher_surname: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SzkolnyTeam {
+    team_code: String,
+}
+
+// Librus Synergia API responses (`/Subjects`, `/Users`) and Szkolny.eu exports (`subjects`,
+// `teachers` and `teams` tables) are all read into this, missing lists are left empty
+#[derive(Deserialize)]
+struct DictionaryFile {
+    #[serde(default, rename = "Subjects")]
+    librus_subjects: Vec<LibrusSubject>,
+    #[serde(default, rename = "Users")]
+    librus_users: Vec<LibrusUser>,
+    #[serde(default, rename = "subjects")]
+    szkolny_subjects: Vec<SzkolnySubject>,
+    #[serde(default, rename = "teachers")]
+    szkolny_teachers: Vec<SzkolnyTeacher>,
+    #[serde(default, rename = "teams")]
+    szkolny_teams: Vec<SzkolnyTeam>,
+}
+
+#[derive(Default)]
+struct Dictionaries {
+    subjects: BTreeMap<String, String>,
+    teachers: BTreeMap<String, String>,
+    teams: Vec<String>,
+}
+
+fn clean(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn format_name(first_name: &str, last_name: &str, name_format: NameFormat) -> String {
+    let first_name = clean(first_name);
+    let last_name = clean(last_name);
+
+    if first_name.is_empty() {
+        return last_name;
+    }
+
+    match name_format {
+        NameFormat::Full => format!("{} {}", first_name, last_name),
+        NameFormat::SurnameFirst => format!("{} {}", last_name, first_name),
+        NameFormat::Initials => {
+            let initials: Vec<_> = first_name
+                .split(' ')
+                .filter_map(|name| name.chars().next())
+                .map(|initial| format!("{}.", initial))
+                .collect();
+            format!("{} {}", initials.join(" "), last_name)
+        }
+    }
+}
+
+fn read_dictionaries(
+    files: &[impl AsRef<Path>],
+    name_format: NameFormat,
+) -> Result<Dictionaries, Box<dyn Error>> {
+    let mut dictionaries = Dictionaries::default();
+
+    for file in files {
+        let file = file.as_ref();
+        let dictionary_file: DictionaryFile = serde_json::from_str(&fs::read_to_string(file)?)
+            .map_err(|e| format!("{}: {}", file.display(), e))?;
+
+        for subject in dictionary_file.librus_subjects {
+            dictionaries
+                .subjects
+                .insert(subject.id.to_string(), clean(&subject.name));
+        }
+        for subject in dictionary_file.szkolny_subjects {
+            dictionaries.subjects.insert(
+                subject.subject_id.to_string(),
+                clean(&subject.subject_long_name),
+            );
+        }
+
+        for user in dictionary_file.librus_users {
+            if user.is_employee {
+                dictionaries.teachers.insert(
+                    user.id.to_string(),
+                    format_name(&user.first_name, &user.last_name, name_format),
+                );
+            }
+        }
+        for teacher in dictionary_file.szkolny_teachers {
+            dictionaries.teachers.insert(
+                teacher.teacher_id.to_string(),
+                format_name(&teacher.teacher_name, &teacher.teacher_surname, name_format),
+            );
+        }
+
+        for team in dictionary_file.szkolny_teams {
+            if !dictionaries.teams.contains(&team.team_code) {
+                dictionaries.teams.push(team.team_code);
+            }
+        }
+    }
+
+    Ok(dictionaries)
+}
+
+fn table_like<'a>(
+    librus: &'a mut Item,
+    key: &str,
+) -> Result<&'a mut dyn TableLike, Box<dyn Error>> {
+    if librus.get(key).is_none() {
+        librus[key] = toml_edit::table();
+    }
+
+    librus[key]
+        .as_table_like_mut()
+        .ok_or_else(|| format!("librus.{} is not a table", key).into())
+}
+
+// Prints every change and returns how many there were
+fn merge_names(
+    table: &mut dyn TableLike,
+    section: &str,
+    names: BTreeMap<String, String>,
+    overwrite: bool,
+) -> usize {
+    let mut changes = 0;
+
+    for (id, name) in names {
+        match table.get(&id).and_then(Item::as_str) {
+            None => {
+                println!("+ [librus.{}] \"{}\" = \"{}\"", section, id, name);
+                table.insert(&id, toml_edit::value(name));
+                changes += 1;
+            }
+            Some(old_name) if old_name != name => {
+                if overwrite {
+                    println!(
+                        "~ [librus.{}] \"{}\" = \"{}\" -> \"{}\"",
+                        section, id, old_name, name
+                    );
+                    table.insert(&id, toml_edit::value(name));
+                    changes += 1;
+                } else {
+                    println!(
+                        "  [librus.{}] \"{}\" = \"{}\" (kept, import has \"{}\")",
+                        section, id, old_name, name
+                    );
+                }
+            }
+            Some(_) => {}
+        }
+    }
+
+    changes
+}
+
+// Keep using `[register]` if the config already does
+fn section(config: &Document) -> &'static str {
+    if config.get("register").is_some() {
+        "register"
+    } else {
+        "librus"
+    }
+}
+
+pub fn config_section(config_path: &Path) -> Result<&'static str, Box<dyn Error>> {
+    let config: Document = fs::read_to_string(config_path)?.parse()?;
+
+    Ok(section(&config))
+}
+
+// Shows what would change in the config and writes it only when asked to. Comments and
+// formatting of the rest of the file are kept.
+pub fn import(
+    config_path: &Path,
+    files: &[impl AsRef<Path>],
+    name_format: NameFormat,
+    overwrite: bool,
+    write: bool,
+) -> Result<(), Box<dyn Error>> {
+    let dictionaries = read_dictionaries(files, name_format)?;
+
+    let mut config: Document = fs::read_to_string(config_path)?.parse()?;
+    let section = section(&config);
+    if config.get(section).is_none() {
+        config[section] = toml_edit::table();
+    }
+    let librus = &mut config[section];
+
+    let mut changes = merge_names(
+        table_like(librus, "subjects")?,
+        "subjects",
+        dictionaries.subjects,
+        overwrite,
+    );
+    changes += merge_names(
+        table_like(librus, "teachers")?,
+        "teachers",
+        dictionaries.teachers,
+        overwrite,
+    );
+
+    if !dictionaries.teams.is_empty() {
+        if librus.get("teams").is_none() {
+            librus["teams"] = toml_edit::value(toml_edit::Array::new());
+        }
+        let teams = librus["teams"]
+            .as_array_mut()
+            .ok_or("librus.teams is not an array")?;
+
+        for team in dictionaries.teams {
+            if !teams
+                .iter()
+                .any(|existing| existing.as_str() == Some(&team))
+            {
+                println!("+ [librus] teams: \"{}\"", team);
+                teams.push(team);
+                changes += 1;
+            }
+        }
+    }
+
+    if changes == 0 {
+        println!("Nothing to change");
+    } else if write {
+        fs::write(config_path, config.to_string())?;
+        println!("Wrote {} changes to {}", changes, config_path.display());
+    } else {
+        println!("{} changes, run again with --write to save them", changes);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_full_names() {
+        assert_eq!(
+            format_name("Jan", "Kowalski", NameFormat::Full),
+            "Jan Kowalski"
+        );
+        assert_eq!(
+            format_name("Jan", "Kowalski", NameFormat::SurnameFirst),
+            "Kowalski Jan"
+        );
+    }
+
+    #[test]
+    fn formats_initials() {
+        assert_eq!(
+            format_name("Jan", "Kowalski", NameFormat::Initials),
+            "J. Kowalski"
+        );
+        assert_eq!(
+            format_name(" Anna  Maria ", "Nowak-Kowalska", NameFormat::Initials),
+            "A. M. Nowak-Kowalska"
+        );
+        assert_eq!(
+            format_name("Łucja", "Nowak", NameFormat::Initials),
+            "Ł. Nowak"
+        );
+    }
+
+    #[test]
+    fn cleans_whitespace() {
+        assert_eq!(
+            format_name("  Jan\t", " Kowalski  ", NameFormat::Full),
+            "Jan Kowalski"
+        );
+    }
+
+    #[test]
+    fn uses_last_name_without_first_name() {
+        assert_eq!(
+            format_name("", "Kowalski", NameFormat::Initials),
+            "Kowalski"
+        );
+        assert_eq!(format_name(" ", "Kowalski", NameFormat::Full), "Kowalski");
+    }
+}
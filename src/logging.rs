@@ -0,0 +1,34 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Set when a JSONL output writes to stdout, so the logs don't end up between its lines
+static TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+pub fn use_stderr() {
+    TO_STDERR.store(true, Ordering::Relaxed);
+}
+
+pub fn to_stderr() -> bool {
+    TO_STDERR.load(Ordering::Relaxed)
+}
+
+// `println!` that goes to stderr when stdout is taken
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if $crate::logging::to_stderr() {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+
+// `print!` that goes to stderr when stdout is taken
+macro_rules! log_inline {
+    ($($arg:tt)*) => {
+        if $crate::logging::to_stderr() {
+            eprint!($($arg)*)
+        } else {
+            print!($($arg)*)
+        }
+    };
+}
@@ -1,22 +1,34 @@
+#[macro_use]
+mod logging;
+
 mod db;
-mod discord_webhook;
+mod event_types;
 mod fcm_wrapper;
+mod icalendar;
+mod import;
+mod mappings;
+mod notification_filter;
 mod notification_types;
+mod profiles;
+mod sanitize;
+mod sinks;
 mod szkolny_api;
 mod szkolny_fcm;
+mod szkolny_push;
+#[cfg(test)]
+mod test_util;
 
+use clap::{Parser, Subcommand};
 use reqwest::header::HeaderMap;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
 use std::{collections::HashMap, fs, path::PathBuf};
+use time_tz::{timezones, Tz};
 
 #[derive(Deserialize)]
 struct GeneralConfig {
     db_path: String,
-}
-
-#[derive(Deserialize)]
-struct DiscordConfig {
-    webhook_url: String,
+    #[serde(default)]
+    dry_run: bool,
 }
 
 #[derive(Deserialize)]
@@ -31,19 +43,98 @@ pub struct LibrusConfig {
     teams: Vec<String>,
     subjects: HashMap<String, String>,
     teachers: HashMap<String, String>,
+    #[serde(default)]
+    event_types: HashMap<String, event_types::EventTypeConfig>,
+    #[serde(default)]
+    profiles: Vec<profiles::ProfileConfig>,
+    #[serde(
+        default = "default_time_zone",
+        deserialize_with = "deserialize_time_zone"
+    )]
+    time_zone: &'static Tz,
+}
+
+fn default_time_zone() -> &'static Tz {
+    timezones::db::europe::WARSAW
+}
+
+fn deserialize_time_zone<'de, D>(deserializer: D) -> Result<&'static Tz, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let name = String::deserialize(deserializer)?;
+    timezones::get_by_name(&name)
+        .ok_or_else(|| serde::de::Error::custom(format!("unknown time zone: {}", name)))
 }
 
 #[derive(Deserialize)]
 pub struct Config {
     general: GeneralConfig,
-    discord: DiscordConfig,
+    discord: Option<sinks::DiscordConfig>,
+    #[serde(default)]
+    outputs: Vec<sinks::OutputConfig>,
     szkolny: SzkolnyConfig,
+    #[serde(default)]
+    admin_messages: notification_types::AdminMessagesConfig,
+    // Subject and teacher IDs don't have to come from Librus, `[register]` works as well
     #[allow(dead_code)]
+    #[serde(alias = "register")]
     librus: LibrusConfig,
 }
 
-fn load_config() -> Config {
-    let config_file = fs::read_to_string("config.toml").unwrap();
+#[derive(Parser)]
+#[command(version, about)]
+struct Args {
+    /// Path to the config file
+    #[arg(short, long, default_value = "config.toml")]
+    config: PathBuf,
+
+    /// Render notifications and print them instead of sending them
+    #[arg(long)]
+    dry_run: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Render a notification payload file through all outputs and print the result
+    Render { payload: PathBuf },
+    /// List subject and teacher IDs seen in events that have no name yet
+    Ids {
+        /// Also list IDs that already have a name
+        #[arg(long)]
+        all: bool,
+        /// Print the IDs as lines to paste into the tables of config.toml
+        #[arg(long)]
+        snippet: bool,
+    },
+    /// Add subjects, teachers and teams from Librus API or Szkolny.eu export JSON files to the config
+    Import {
+        files: Vec<PathBuf>,
+        #[arg(long, value_enum, default_value = "full")]
+        name_format: import::NameFormat,
+        /// Replace names that are already in the config
+        #[arg(long)]
+        overwrite: bool,
+        /// Save the changes instead of only showing them
+        #[arg(long)]
+        write: bool,
+    },
+    /// Store the name of a subject or teacher ID in the database
+    Map {
+        /// Profile the ID belongs to, the main section is used when not set
+        #[arg(long)]
+        profile: Option<String>,
+        kind: notification_types::IdKind,
+        id: String,
+        name: String,
+    },
+}
+
+fn load_config(path: &PathBuf) -> Config {
+    let config_file = fs::read_to_string(path).unwrap();
     let config: Config = toml::from_str(&config_file).unwrap();
     config
 }
@@ -52,15 +143,63 @@ static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_P
 
 #[tokio::main]
 async fn main() {
-    println!("Starting app...");
+    let args = Args::parse();
+
+    if let Some(Command::Import {
+        files,
+        name_format,
+        overwrite,
+        write,
+    }) = &args.command
+    {
+        import::import(&args.config, files, *name_format, *overwrite, *write).unwrap();
+        return;
+    }
+
+    // Loaded before anything is printed, it decides where the logs go
+    let mut config = load_config(&args.config);
+    let dry_run = args.dry_run || config.general.dry_run;
+    if config
+        .outputs
+        .iter()
+        .any(sinks::OutputConfig::writes_to_stdout)
+    {
+        logging::use_stderr();
+    }
 
-    print!(" > Loading config... ");
-    let config = load_config();
-    println!("✓");
+    log!("Starting app...");
+    log!(" > Loading config... ✓");
 
-    print!(" > Connecting to database... ");
+    log_inline!(" > Connecting to database... ");
     let database = db::connect(PathBuf::from(config.general.db_path)).unwrap();
-    println!("✓");
+    mappings::merge(&database, &mut config.librus).unwrap();
+    log!("✓");
+
+    match &args.command {
+        Some(Command::Ids { all, snippet }) => {
+            let section = snippet.then(|| import::config_section(&args.config).unwrap());
+            mappings::print_seen_ids(&database, &config.librus, *all, section).unwrap();
+            return;
+        }
+        Some(Command::Map {
+            profile,
+            kind,
+            id,
+            name,
+        }) => {
+            db::set_mapping(
+                &database,
+                profile.as_deref().unwrap_or(""),
+                kind.as_str(),
+                id,
+                name,
+            )
+            .unwrap();
+            println!("Saved, restart the bot for it to take effect");
+            return;
+        }
+        _ => {}
+    }
 
     let mut default_headers = HeaderMap::new();
     default_headers.insert("X-ApiKey", config.szkolny.api_key.parse().unwrap());
@@ -71,41 +210,68 @@ async fn main() {
         .build()
         .unwrap();
 
+    // Kept separate so the Szkolny API key is never sent to the outputs
+    let sink_client = reqwest::Client::builder()
+        .user_agent(APP_USER_AGENT)
+        .build()
+        .unwrap();
+
+    let mut output_configs = Vec::new();
+    if let Some(discord_config) = config.discord {
+        output_configs.push(sinks::OutputConfig::from(discord_config));
+    }
+    output_configs.extend(config.outputs);
+    let outputs = sinks::build_outputs(output_configs);
+
+    // Admin messages would otherwise be dropped without any trace
+    if config.admin_messages.uses_admin_outputs() && !outputs.iter().any(|output| output.admin()) {
+        eprintln!(
+            "Warning: some admin_messages are sent only to admin outputs, but no output has admin = true"
+        );
+    }
+
+    if let Some(Command::Render { payload }) = args.command {
+        let context = sinks::SinkContext {
+            client: &sink_client,
+            database: &database,
+            librus_config: &config.librus,
+        };
+        szkolny_fcm::render_payload_file(&payload, &outputs, &context, &config.admin_messages)
+            .await
+            .unwrap();
+        return;
+    }
+
     let fcm_registration: fcm_push_listener::Registration =
         match db::get_data(&database, "fcm_registration").unwrap() {
             Some(registration) => registration,
             None => {
-                print!(" > Registering with FCM... ");
+                log_inline!(" > Registering with FCM... ");
                 let registration = szkolny_fcm::register(&config.szkolny.fcm_sender_id)
                     .await
                     .unwrap();
                 db::set_data(&database, "fcm_registration", &registration).unwrap();
-                println!("✓");
+                log!("✓");
                 registration
             }
         };
 
-    println!("FCM token: {}", fcm_registration.fcm_token);
+    log!("FCM token: {}", fcm_registration.fcm_token);
 
     if let Some(browser_id) = db::get_data_raw(&database, "browser_id").unwrap() {
-        println!(" > Contacting api.szkolny.eu...");
-        szkolny_api::print_registered_devices(
-            &http_client,
-            &String::from_utf8_lossy(&browser_id).to_string(),
-        )
-        .await;
-        println!(
+        log!(" > Contacting api.szkolny.eu...");
+        szkolny_api::print_registered_devices(&http_client, &String::from_utf8_lossy(&browser_id))
+            .await;
+        log!(
             "Pair token: {}",
             String::from_utf8_lossy(&db::get_data_raw(&database, "pair_token").unwrap().unwrap())
-                .to_string()
         );
-        println!(
+        log!(
             "Browser ID: {}",
             String::from_utf8_lossy(&db::get_data_raw(&database, "browser_id").unwrap().unwrap())
-                .to_string()
         );
     } else {
-        println!(" > Registering with Szkolny.eu webPush API... ");
+        log!(" > Registering with Szkolny.eu webPush API... ");
         let (browser_id, pair_token) =
             szkolny_api::register_browser(&http_client, &fcm_registration.fcm_token).await;
 
@@ -113,13 +279,19 @@ async fn main() {
         db::set_data_raw(&database, "pair_token", pair_token.as_bytes().to_vec()).unwrap();
     }
 
-    println!("Starting FCM listener...");
+    if dry_run {
+        log!("Dry run enabled, notifications will not be sent");
+    }
+
+    log!("Starting FCM listener...");
     szkolny_fcm::run(
         fcm_registration,
         database,
-        config.discord.webhook_url,
-        &http_client,
+        &outputs,
+        &sink_client,
         &config.librus,
+        &config.admin_messages,
+        dry_run,
     )
     .await;
 }
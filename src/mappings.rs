@@ -0,0 +1,225 @@
+use std::error::Error;
+
+use time::{format_description::FormatItem, macros::format_description, OffsetDateTime};
+use time_tz::OffsetDateTimeExt;
+
+use crate::{db, notification_types::IdKind, profiles, LibrusConfig};
+
+const LAST_SEEN_FORMAT: &[FormatItem] = format_description!("[year]-[month]-[day] [hour]:[minute]");
+
+// Names stored in the database take precedence over the ones from the config
+pub fn merge(
+    database: &rusqlite::Connection,
+    librus_config: &mut LibrusConfig,
+) -> rusqlite::Result<()> {
+    librus_config
+        .subjects
+        .extend(db::get_mappings(database, "", IdKind::Subject.as_str())?);
+    librus_config
+        .teachers
+        .extend(db::get_mappings(database, "", IdKind::Teacher.as_str())?);
+
+    for profile in &mut librus_config.profiles {
+        profile.subjects.extend(db::get_mappings(
+            database,
+            &profile.name,
+            IdKind::Subject.as_str(),
+        )?);
+        profile.teachers.extend(db::get_mappings(
+            database,
+            &profile.name,
+            IdKind::Teacher.as_str(),
+        )?);
+    }
+
+    Ok(())
+}
+
+// Looks the ID up the same way notifications do, in the profile first and then the main section
+fn find_name<'a>(librus_config: &'a LibrusConfig, seen_id: &db::SeenId) -> Option<&'a String> {
+    let profile = profiles::get_profile(librus_config, &seen_id.profile);
+    let kind = if seen_id.kind == IdKind::Subject.as_str() {
+        IdKind::Subject
+    } else {
+        IdKind::Teacher
+    };
+
+    profiles::find_name(librus_config, profile, kind, &seen_id.id)
+}
+
+fn profile_prefix(seen_id: &db::SeenId) -> String {
+    if seen_id.profile.is_empty() {
+        String::new()
+    } else {
+        format!("[{}] ", seen_id.profile)
+    }
+}
+
+pub fn print_seen_ids(
+    database: &rusqlite::Connection,
+    librus_config: &LibrusConfig,
+    all: bool,
+    // Config section (`librus` or `register`) to print a snippet for
+    snippet_section: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let seen_ids: Vec<_> = db::get_seen_ids(database)?
+        .into_iter()
+        .filter(|seen_id| all || find_name(librus_config, seen_id).is_none())
+        .collect();
+
+    if seen_ids.is_empty() {
+        println!("No IDs to show");
+        return Ok(());
+    }
+
+    if let Some(section) = snippet_section {
+        println!("{}", snippet(&seen_ids, librus_config, section));
+        return Ok(());
+    }
+
+    for seen_id in &seen_ids {
+        let name = find_name(librus_config, seen_id)
+            .map(String::as_str)
+            .unwrap_or("?");
+        let last_seen = OffsetDateTime::from_unix_timestamp(seen_id.last_seen)?
+            .to_timezone(librus_config.time_zone)
+            .format(LAST_SEEN_FORMAT)?;
+
+        println!(
+            "{}{} {}: {} (seen {} times, last on {})",
+            profile_prefix(seen_id),
+            seen_id.kind,
+            seen_id.id,
+            name,
+            seen_id.seen_count,
+            last_seen
+        );
+        println!(
+            "    \"{}\" shared by {}",
+            one_line(&seen_id.topic),
+            seen_id.shared_by
+        );
+    }
+
+    Ok(())
+}
+
+// Prints `"id" = "name"` lines to paste into the tables of config.toml after filling in the
+// names. The tables already exist in every config, so only the lines are printed, each group
+// under a comment naming its table.
+fn snippet(seen_ids: &[db::SeenId], librus_config: &LibrusConfig, section: &str) -> String {
+    let mut profiles: Vec<&str> = seen_ids
+        .iter()
+        .map(|seen_id| seen_id.profile.as_str())
+        .collect();
+    profiles.dedup();
+
+    let mut lines = Vec::new();
+
+    for profile in profiles {
+        for (kind, table) in [(IdKind::Subject, "subjects"), (IdKind::Teacher, "teachers")] {
+            let ids: Vec<_> = seen_ids
+                .iter()
+                .filter(|seen_id| seen_id.profile == profile && seen_id.kind == kind.as_str())
+                .collect();
+
+            if ids.is_empty() {
+                continue;
+            }
+
+            if profile.is_empty() {
+                lines.push(format!("# [{}.{}]", section, table));
+            } else {
+                lines.push(format!(
+                    "# [{}.profiles.{}] of profile {}",
+                    section,
+                    table,
+                    toml::Value::String(profile.to_owned())
+                ));
+            }
+
+            for seen_id in ids {
+                let name = find_name(librus_config, seen_id)
+                    .cloned()
+                    .unwrap_or_default();
+
+                lines.push(format!(
+                    "{} = {} # \"{}\" shared by {}",
+                    toml::Value::String(seen_id.id.clone()),
+                    toml::Value::String(name),
+                    one_line(&seen_id.topic),
+                    one_line(&seen_id.shared_by)
+                ));
+            }
+            lines.push(String::new());
+        }
+    }
+
+    lines.join("\n")
+}
+
+fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_util;
+
+    fn seen_id(profile: &str, kind: IdKind, id: &str) -> db::SeenId {
+        db::SeenId {
+            profile: profile.to_owned(),
+            kind: kind.as_str().to_owned(),
+            id: id.to_owned(),
+            topic: "Funkcje\nkwadratowe".to_owned(),
+            shared_by: "Jan Kowalski".to_owned(),
+            seen_count: 1,
+            last_seen: 0,
+        }
+    }
+
+    #[test]
+    fn prints_snippet_lines_only() {
+        let seen_ids = [
+            seen_id("", IdKind::Subject, "123"),
+            seen_id("", IdKind::Subject, "124"),
+            seen_id("", IdKind::Teacher, "7"),
+            seen_id("vulcan", IdKind::Subject, "5"),
+        ];
+
+        assert_eq!(
+            snippet(&seen_ids, &test_util::librus_config(), "register"),
+            [
+                "# [register.subjects]",
+                r#""123" = "Matematyka" # "Funkcje kwadratowe" shared by Jan Kowalski"#,
+                r#""124" = "" # "Funkcje kwadratowe" shared by Jan Kowalski"#,
+                "",
+                "# [register.teachers]",
+                r#""7" = "" # "Funkcje kwadratowe" shared by Jan Kowalski"#,
+                "",
+                r#"# [register.profiles.subjects] of profile "vulcan""#,
+                r#""5" = "" # "Funkcje kwadratowe" shared by Jan Kowalski"#,
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn snippet_lines_are_valid_toml() {
+        let seen_ids = [seen_id("", IdKind::Subject, "123")];
+        let snippet = snippet(&seen_ids, &test_util::librus_config(), "librus");
+
+        let config = format!(
+            "[librus]\nteams = []\nteachers = {{}}\n[librus.subjects]\n\"1\" = \"Fizyka\"\n{}",
+            snippet
+        );
+        let config: toml::Table = toml::from_str(&config).unwrap();
+        assert_eq!(
+            config["librus"]["subjects"]["123"].as_str(),
+            Some("Matematyka")
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use serde::Deserialize;
+
+use crate::notification_types::NotificationEmbed;
+
+#[derive(Deserialize, Default)]
+pub struct NotificationFilter {
+    notification_type: Option<String>,
+    event_type: Option<i32>,
+    subject: Option<String>,
+    team: Option<String>,
+    profile: Option<String>,
+}
+
+impl NotificationFilter {
+    pub fn matches(&self, embed: &NotificationEmbed) -> bool {
+        if let Some(notification_type) = &self.notification_type {
+            if notification_type != &embed.notification_type {
+                return false;
+            }
+        }
+
+        if let Some(event_type) = self.event_type {
+            if embed.event_type != Some(event_type) {
+                return false;
+            }
+        }
+
+        // Subject IDs are only unique within a profile, so a subject without a profile only
+        // matches notifications from the main section
+        if let Some(subject) = &self.subject {
+            if embed.subject_id.map(|id| id.to_string()).as_ref() != Some(subject)
+                || embed.profile != self.profile
+            {
+                return false;
+            }
+        }
+
+        if let Some(profile) = &self.profile {
+            if embed.profile.as_ref() != Some(profile) {
+                return false;
+            }
+        }
+
+        if let Some(team) = &self.team {
+            if embed.team_code.as_ref() != Some(team) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::szkolny_push::PushKind;
+
+    fn embed(subject_id: i32, profile: Option<&str>) -> NotificationEmbed {
+        NotificationEmbed {
+            kind: PushKind::SharedEvent,
+            notification_type: "sharedEvent".to_owned(),
+            event_type: Some(1),
+            subject_id: Some(subject_id),
+            team_code: Some("2a".to_owned()),
+            profile: profile.map(str::to_owned),
+            author: None,
+            description: None,
+            code_block: None,
+            fields: vec![],
+            color: None,
+            calendar_event: None,
+        }
+    }
+
+    fn filter(toml: &str) -> NotificationFilter {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn subject_without_profile_matches_main_section_only() {
+        let filter = filter(r#"subject = "123""#);
+
+        assert!(filter.matches(&embed(123, None)));
+        assert!(!filter.matches(&embed(123, Some("vulcan"))));
+    }
+
+    #[test]
+    fn subject_matches_within_profile() {
+        let filter = filter(
+            r#"
+            subject = "123"
+            profile = "vulcan"
+            "#,
+        );
+
+        assert!(filter.matches(&embed(123, Some("vulcan"))));
+        assert!(!filter.matches(&embed(123, None)));
+        assert!(!filter.matches(&embed(123, Some("librus"))));
+    }
+
+    #[test]
+    fn profile_matches_any_subject() {
+        let filter = filter(r#"profile = "vulcan""#);
+
+        assert!(filter.matches(&embed(1, Some("vulcan"))));
+        assert!(!filter.matches(&embed(1, None)));
+    }
+}
@@ -1,135 +1,488 @@
-use time::{Date, Month, Time};
+use std::error::Error;
 
-use crate::LibrusConfig;
+use serde::Deserialize;
+use time::{Date, Duration, Month, OffsetDateTime, PrimitiveDateTime, Time};
+use time_tz::{Offset, PrimitiveDateTimeExt, TimeZone};
+
+use crate::{
+    event_types,
+    icalendar::CalendarEvent,
+    profiles::{self, ProfileConfig},
+    szkolny_push::{PushKind, SzkolnyPush},
+    LibrusConfig,
+};
+
+// Szkolny event types for tests and short tests
+pub const TEST_EVENT_TYPES: &[i32] = &[1, 2];
+
+// What to do with administrative messages that Szkolny sends to every browser
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MessagePolicy {
+    // Send to the regular outputs, like any other notification
+    Forward,
+    // Send only to outputs marked with `admin = true`
+    Admin,
+    Drop,
+}
+
+fn default_message_policy() -> MessagePolicy {
+    MessagePolicy::Forward
+}
+
+#[derive(Deserialize)]
+pub struct AdminMessagesConfig {
+    #[serde(default = "default_message_policy")]
+    server_message: MessagePolicy,
+    #[serde(default = "default_message_policy")]
+    feedback_message: MessagePolicy,
+    #[serde(default = "default_message_policy")]
+    app_update: MessagePolicy,
+    #[serde(default = "default_message_policy")]
+    user_action_required: MessagePolicy,
+    #[serde(default = "default_message_policy")]
+    register_availability: MessagePolicy,
+}
+
+impl Default for AdminMessagesConfig {
+    fn default() -> Self {
+        AdminMessagesConfig {
+            server_message: default_message_policy(),
+            feedback_message: default_message_policy(),
+            app_update: default_message_policy(),
+            user_action_required: default_message_policy(),
+            register_availability: default_message_policy(),
+        }
+    }
+}
+
+impl AdminMessagesConfig {
+    pub fn uses_admin_outputs(&self) -> bool {
+        [
+            self.server_message,
+            self.feedback_message,
+            self.app_update,
+            self.user_action_required,
+            self.register_availability,
+        ]
+        .contains(&MessagePolicy::Admin)
+    }
+
+    fn policy(&self, push: &SzkolnyPush) -> MessagePolicy {
+        match push {
+            SzkolnyPush::ServerMessage(_) => self.server_message,
+            SzkolnyPush::FeedbackMessage(_) => self.feedback_message,
+            SzkolnyPush::AppUpdate { .. } => self.app_update,
+            SzkolnyPush::UserActionRequired(_) => self.user_action_required,
+            SzkolnyPush::RegisterAvailability(_) => self.register_availability,
+            _ => MessagePolicy::Forward,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SzkolnyNotification {
+    title: String,
+    message: String,
+}
+
+pub struct ProcessedNotification {
+    pub id: String,
+    pub title: String,
+    pub message: String,
+    pub payload: serde_json::Value,
+    pub embed: NotificationEmbed,
+    // Meant only for outputs marked as admin outputs
+    pub admin_only: bool,
+    pub seen_ids: Vec<SeenId>,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum IdKind {
+    Subject,
+    Teacher,
+}
+
+impl IdKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IdKind::Subject => "subject",
+            IdKind::Teacher => "teacher",
+        }
+    }
+}
+
+// A subject or teacher ID used by an event, recorded so that missing names can be filled in
+pub struct SeenId {
+    // Name of the matched profile, empty for the main section
+    pub profile: String,
+    pub kind: IdKind,
+    pub id: String,
+    pub topic: String,
+    pub shared_by: String,
+}
+
+pub fn process_message(
+    id: &str,
+    json: &str,
+    librus_config: &LibrusConfig,
+    admin_messages: &AdminMessagesConfig,
+) -> Result<Option<ProcessedNotification>, Box<dyn Error>> {
+    let fcm_message: serde_json::Value = serde_json::from_str(json)?;
+    let payload = fcm_message["data"].clone();
+
+    let push = SzkolnyPush::parse(&payload);
+    if let SzkolnyPush::SyncNotify | SzkolnyPush::Ping = push {
+        return Ok(None);
+    }
+
+    let policy = admin_messages.policy(&push);
+    if policy == MessagePolicy::Drop {
+        return Ok(None);
+    }
+
+    let szkolny_notification: SzkolnyNotification = serde_json::from_value(payload.clone())?;
+    let mut seen_ids = Vec::new();
+    let embed = process_notification(
+        &push,
+        &payload,
+        &szkolny_notification.title,
+        librus_config,
+        &mut seen_ids,
+    );
+
+    Ok(Some(ProcessedNotification {
+        id: id.to_owned(),
+        title: szkolny_notification.title,
+        message: szkolny_notification.message,
+        payload,
+        embed,
+        admin_only: policy == MessagePolicy::Admin,
+        seen_ids,
+    }))
+}
 
 pub fn process_notification(
-    notification: &serde_json::Value,
+    push: &SzkolnyPush,
+    payload: &serde_json::Value,
+    title: &str,
     librus_config: &LibrusConfig,
+    seen_ids: &mut Vec<SeenId>,
 ) -> NotificationEmbed {
-    let notification_type = notification["type"].as_str().unwrap_or("null");
+    let team_code = match push {
+        SzkolnyPush::SharedEvent { event } => Some(event.team_code.as_str()),
+        SzkolnyPush::UnsharedEvent {
+            unshare_team_code, ..
+        }
+        | SzkolnyPush::UnsharedNote {
+            unshare_team_code, ..
+        } => Some(unshare_team_code.as_str()),
+        SzkolnyPush::SharedNote {
+            share_team_code, ..
+        } => Some(share_team_code.as_str()),
+        _ => None,
+    };
+    let profile = profiles::find_profile(librus_config, title, team_code);
 
-    let processor: Box<dyn NotificationProcessor> = match notification_type {
-        "sharedEvent" => Box::new(shared_event_notification::SharedEventProcessor {}),
-        "unsharedEvent" => Box::new(unshared_event_notification::UnsharedEventProcessor {}),
-        _ => Box::new(other_notification::OtherNotificationProcessor {}),
+    let mut embed = match push {
+        SzkolnyPush::SharedEvent { event } => {
+            shared_event_notification::process(event, profile, librus_config, seen_ids)
+        }
+        SzkolnyPush::UnsharedEvent {
+            unshare_team_code,
+            event_id,
+        } => unshared_event_notification::process(unshare_team_code, event_id),
+        SzkolnyPush::SharedNote {
+            share_team_code,
+            note,
+        } => shared_note_notification::process(share_team_code, note),
+        SzkolnyPush::UnsharedNote {
+            unshare_team_code,
+            note_id,
+        } => unshared_note_notification::process(unshare_team_code, note_id),
+        SzkolnyPush::ServerMessage(message) => {
+            admin_notification::process_message(PushKind::ServerMessage, message)
+        }
+        SzkolnyPush::FeedbackMessage(message) => {
+            admin_notification::process_message(PushKind::FeedbackMessage, message)
+        }
+        SzkolnyPush::UserActionRequired(message) => {
+            admin_notification::process_message(PushKind::UserActionRequired, message)
+        }
+        SzkolnyPush::RegisterAvailability(message) => {
+            admin_notification::process_message(PushKind::RegisterAvailability, message)
+        }
+        SzkolnyPush::AppUpdate { update } => admin_notification::process_app_update(update),
+        _ => other_notification::process(payload),
     };
 
-    processor.process(notification, librus_config)
+    embed.profile = profile.map(|profile| profile.name.clone());
+
+    embed
+}
+
+pub enum TimestampStyle {
+    Date,
+    DateTime,
+    Time,
+}
+
+pub struct NotificationTimestamp {
+    pub time: OffsetDateTime,
+    pub style: TimestampStyle,
 }
 
 pub struct NotificationEmbedField {
     pub name: String,
     pub value: String,
+    pub user_supplied: bool,
+    pub timestamp: Option<NotificationTimestamp>,
 }
 
 pub struct NotificationEmbed {
+    pub kind: PushKind,
+    pub notification_type: String,
+    pub event_type: Option<i32>,
+    pub subject_id: Option<i32>,
+    pub team_code: Option<String>,
+    // Name of the profile the notification was matched to
+    pub profile: Option<String>,
     pub author: Option<String>,
     pub description: Option<String>,
+    pub code_block: Option<String>,
     pub fields: Vec<NotificationEmbedField>,
+    pub color: Option<u32>,
+    pub calendar_event: Option<CalendarEvent>,
 }
 
-trait NotificationProcessor {
-    fn process(
-        &self,
-        notification: &serde_json::Value,
-        librus_config: &LibrusConfig,
-    ) -> NotificationEmbed;
-}
-
-fn szkolny_date_convert(date: u64) -> Date {
-    let year = (date / 10000) as i32;
+// Dates come from other users, so they aren't guaranteed to be valid
+fn szkolny_date_convert(date: u64) -> Option<Date> {
+    let year = i32::try_from(date / 10000).ok()?;
     let month = ((date % 10000) / 100) as u8;
     let day = (date % 100) as u8;
 
-    Date::from_calendar_date(year, Month::try_from(month).unwrap(), day).unwrap()
+    Date::from_calendar_date(year, Month::try_from(month).ok()?, day).ok()
 }
 
-fn szkolny_time_convert(time: u64) -> Time {
-    let hour = (time / 10000) as u8;
+fn szkolny_time_convert(time: u64) -> Option<Time> {
+    let hour = u8::try_from(time / 10000).ok()?;
     let minute = ((time % 10000) / 100) as u8;
     let second = (time % 100) as u8;
 
-    Time::from_hms(hour, minute, second).unwrap()
+    Time::from_hms(hour, minute, second).ok()
+}
+
+fn szkolny_datetime_convert(
+    date: u64,
+    time: Option<u64>,
+    librus_config: &LibrusConfig,
+) -> Option<OffsetDateTime> {
+    let datetime = PrimitiveDateTime::new(
+        szkolny_date_convert(date)?,
+        match time {
+            Some(time) => szkolny_time_convert(time)?,
+            None => Time::MIDNIGHT,
+        },
+    );
+
+    // Times that fall into a DST gap don't exist locally, they are moved forward by the gap
+    // using the offset from before it
+    let time_zone = librus_config.time_zone;
+    Some(
+        datetime
+            .assume_timezone(time_zone)
+            .take_first()
+            .unwrap_or_else(|| {
+                let offset_before = time_zone
+                    .get_offset_utc(&(datetime.assume_utc() - Duration::days(1)))
+                    .to_utc();
+                datetime.assume_offset(offset_before)
+            }),
+    )
 }
 
 mod shared_event_notification {
     use super::*;
 
-    use serde::Deserialize;
-
-    #[derive(Deserialize)]
-    #[serde(rename_all = "camelCase")]
-    struct SzkolnyEvent {
-        #[serde(rename = "type")]
-        event_type: i32,
-        subject_id: i32,
-        shared_by_name: String,
-        teacher_id: i32,
-        team_code: String,
-        topic: String,
-        start_time: Option<u64>,
-        id: u64,
-        event_date: u64,
-    }
-
-    pub struct SharedEventProcessor {}
-
-    impl NotificationProcessor for SharedEventProcessor {
-        fn process(
-            &self,
-            notification: &serde_json::Value,
-            librus_config: &LibrusConfig,
-        ) -> NotificationEmbed {
-            let event: SzkolnyEvent =
-                serde_json::from_str(notification["event"].as_str().unwrap()).unwrap();
-
-            let embed = NotificationEmbed {
-                author: Some(event.shared_by_name),
-                description: Some(event.topic),
-                fields: vec![
-                    NotificationEmbedField {
-                        name: "Grupa".to_owned(),
-                        value: event.team_code.to_string(),
+    use crate::szkolny_push::SharedEvent;
+
+    fn lookup(
+        librus_config: &LibrusConfig,
+        profile: Option<&ProfileConfig>,
+        kind: IdKind,
+        id: i32,
+        event: &SharedEvent,
+        seen_ids: &mut Vec<SeenId>,
+    ) -> String {
+        let id = id.to_string();
+
+        let name = match profiles::find_name(librus_config, profile, kind, &id) {
+            Some(name) => name.clone(),
+            None => {
+                eprintln!(
+                    "  -> Unknown {} ID {}{}, add it to the config or use the `map` command",
+                    kind.as_str(),
+                    id,
+                    profile
+                        .map(|profile| format!(" in profile {}", profile.name))
+                        .unwrap_or_default()
+                );
+
+                match kind {
+                    IdKind::Subject => format!("Nieznany przedmiot (#{})", id),
+                    IdKind::Teacher => format!("Nieznany nauczyciel (#{})", id),
+                }
+            }
+        };
+
+        seen_ids.push(SeenId {
+            profile: profiles::profile_key(profile).to_owned(),
+            kind,
+            id,
+            topic: event.topic.clone(),
+            shared_by: event.shared_by_name.clone(),
+        });
+
+        name
+    }
+
+    pub fn process(
+        event: &SharedEvent,
+        profile: Option<&ProfileConfig>,
+        librus_config: &LibrusConfig,
+        seen_ids: &mut Vec<SeenId>,
+    ) -> NotificationEmbed {
+        // Without a valid date the raw values are shown and no calendar event is made
+        let event_time =
+            szkolny_datetime_convert(event.event_date, event.start_time, librus_config);
+        if event_time.is_none() {
+            eprintln!(
+                "  -> Invalid date {} {:?} in event {}",
+                event.event_date, event.start_time, event.id
+            );
+        }
+
+        let subject = if event.subject_id != -1 {
+            lookup(
+                librus_config,
+                profile,
+                IdKind::Subject,
+                event.subject_id,
+                event,
+                seen_ids,
+            )
+        } else {
+            "Brak przedmiotu".to_owned()
+        };
+
+        let teacher = if event.teacher_id != -1 {
+            lookup(
+                librus_config,
+                profile,
+                IdKind::Teacher,
+                event.teacher_id,
+                event,
+                seen_ids,
+            )
+        } else {
+            "Brak nauczyciela".to_owned()
+        };
+
+        let event_type = event_types::event_type(event.event_type, librus_config, profile);
+
+        let calendar_event = event_time.map(|event_time| CalendarEvent::Publish {
+            event_id: event.id.to_string(),
+            start: event_time,
+            all_day: event.start_time.is_none(),
+            summary: if event.subject_id != -1 {
+                format!("{}: {}", subject, event.topic)
+            } else {
+                event.topic.clone()
+            },
+            description: format!(
+                "{}\n\nPrzedmiot: {}\nNauczyciel: {}\nGrupa: {}\nUdostępnione przez: {}",
+                event.topic, subject, teacher, event.team_code, event.shared_by_name
+            ),
+            categories: (event.subject_id != -1).then(|| subject.clone()),
+        });
+
+        NotificationEmbed {
+            kind: PushKind::SharedEvent,
+            notification_type: PushKind::SharedEvent.type_name().to_owned(),
+            event_type: Some(event.event_type),
+            subject_id: (event.subject_id != -1).then_some(event.subject_id),
+            team_code: Some(event.team_code.clone()),
+            profile: None,
+            author: Some(event.shared_by_name.clone()),
+            description: Some(event.topic.clone()),
+            code_block: None,
+            fields: vec![
+                NotificationEmbedField {
+                    name: "Grupa".to_owned(),
+                    value: event.team_code.clone(),
+                    user_supplied: true,
+                    timestamp: None,
+                },
+                NotificationEmbedField {
+                    name: "Przedmiot".to_owned(),
+                    value: subject,
+                    user_supplied: false,
+                    timestamp: None,
+                },
+                NotificationEmbedField {
+                    name: "Nauczyciel".to_owned(),
+                    value: teacher,
+                    user_supplied: false,
+                    timestamp: None,
+                },
+                NotificationEmbedField {
+                    name: "Data".to_owned(),
+                    value: match szkolny_date_convert(event.event_date) {
+                        Some(date) => date.to_string(),
+                        None => event.event_date.to_string(),
                     },
-                    NotificationEmbedField {
-                        name: "Przedmiot".to_owned(),
-                        value: if event.subject_id != -1 {
-                            librus_config.subjects[&event.subject_id.to_string()].clone()
-                        } else {
-                            "Brak przedmiotu".to_owned()
+                    user_supplied: false,
+                    timestamp: event_time.map(|time| NotificationTimestamp {
+                        time,
+                        style: match event.start_time {
+                            Some(_) => TimestampStyle::DateTime,
+                            None => TimestampStyle::Date,
                         },
-                    },
-                    NotificationEmbedField {
-                        name: "Nauczyciel".to_owned(),
-                        value: if event.teacher_id != -1 {
-                            librus_config.teachers[&event.teacher_id.to_string()].clone()
-                        } else {
-                            "Brak nauczyciela".to_owned()
+                    }),
+                },
+                NotificationEmbedField {
+                    name: "Godzina".to_owned(),
+                    value: match event.start_time {
+                        Some(time) => match szkolny_time_convert(time) {
+                            Some(time) => time.to_string(),
+                            None => time.to_string(),
                         },
+                        None => "Cały dzień".to_string(),
                     },
-                    NotificationEmbedField {
-                        name: "Data".to_owned(),
-                        value: szkolny_date_convert(event.event_date).to_string(),
-                    },
-                    NotificationEmbedField {
-                        name: "Godzina".to_owned(),
-                        value: match event.start_time {
-                            Some(time) => szkolny_time_convert(time).to_string(),
-                            None => "Cały dzień".to_string(),
-                        },
-                    },
-                    NotificationEmbedField {
-                        name: "Typ".to_owned(),
-                        value: event.event_type.to_string(),
-                    },
-                    NotificationEmbedField {
-                        name: "ID".to_owned(),
-                        value: event.id.to_string(),
-                    },
-                ],
-            };
-
-            embed
+                    user_supplied: false,
+                    timestamp: event
+                        .start_time
+                        .and(event_time)
+                        .map(|time| NotificationTimestamp {
+                            time,
+                            style: TimestampStyle::Time,
+                        }),
+                },
+                NotificationEmbedField {
+                    name: "Typ".to_owned(),
+                    value: event_type.label(),
+                    user_supplied: false,
+                    timestamp: None,
+                },
+                NotificationEmbedField {
+                    name: "ID".to_owned(),
+                    value: event.id.to_string(),
+                    user_supplied: false,
+                    timestamp: None,
+                },
+            ],
+            color: event_type.color,
+            calendar_event,
         }
     }
 }
@@ -137,30 +490,251 @@ mod shared_event_notification {
 mod unshared_event_notification {
     use super::*;
 
-    pub struct UnsharedEventProcessor {}
-
-    impl NotificationProcessor for UnsharedEventProcessor {
-        fn process(
-            &self,
-            notification: &serde_json::Value,
-            _librus_config: &LibrusConfig,
-        ) -> NotificationEmbed {
-            let embed = NotificationEmbed {
-                author: None,
-                description: None,
-                fields: vec![
-                    NotificationEmbedField {
-                        name: "Grupa".to_owned(),
-                        value: notification["unshareTeamCode"].as_str().unwrap().to_owned(),
-                    },
-                    NotificationEmbedField {
-                        name: "ID".to_owned(),
-                        value: notification["eventId"].as_str().unwrap().to_owned(),
-                    },
-                ],
-            };
+    pub fn process(unshare_team_code: &str, event_id: &str) -> NotificationEmbed {
+        NotificationEmbed {
+            kind: PushKind::UnsharedEvent,
+            notification_type: PushKind::UnsharedEvent.type_name().to_owned(),
+            event_type: None,
+            subject_id: None,
+            team_code: Some(unshare_team_code.to_owned()),
+            profile: None,
+            author: None,
+            description: None,
+            code_block: None,
+            fields: vec![
+                NotificationEmbedField {
+                    name: "Grupa".to_owned(),
+                    value: unshare_team_code.to_owned(),
+                    user_supplied: true,
+                    timestamp: None,
+                },
+                NotificationEmbedField {
+                    name: "ID".to_owned(),
+                    value: event_id.to_owned(),
+                    user_supplied: true,
+                    timestamp: None,
+                },
+            ],
+            color: None,
+            calendar_event: Some(CalendarEvent::Cancel {
+                event_id: event_id.to_owned(),
+            }),
+        }
+    }
+}
+
+mod shared_note_notification {
+    use super::*;
+
+    use crate::szkolny_push::SharedNote;
+
+    fn owner_name(note: &SharedNote) -> Option<String> {
+        let owner_id = note.owner_id?;
+
+        let owner = match note.owner_type.as_deref()? {
+            "NONE" => return None,
+            "DAY" => {
+                return Some(match szkolny_date_convert(owner_id) {
+                    Some(date) => format!("Dzień {}", date),
+                    None => format!("Dzień #{}", owner_id),
+                })
+            }
+            "EVENT" => "Wydarzenie",
+            "LESSON" => "Lekcja",
+            "MESSAGE" => "Wiadomość",
+            "ANNOUNCEMENT" => "Ogłoszenie",
+            other => other,
+        };
+
+        Some(format!("{} (#{})", owner, owner_id))
+    }
+
+    pub fn process(share_team_code: &str, note: &SharedNote) -> NotificationEmbed {
+        let mut fields = vec![NotificationEmbedField {
+            name: "Grupa".to_owned(),
+            value: share_team_code.to_owned(),
+            user_supplied: true,
+            timestamp: None,
+        }];
+
+        if let Some(topic) = &note.topic {
+            fields.push(NotificationEmbedField {
+                name: "Tytuł".to_owned(),
+                value: topic.clone(),
+                user_supplied: true,
+                timestamp: None,
+            });
+        }
 
-            embed
+        if let Some(owner) = owner_name(note) {
+            // Unknown owner types are shown as sent
+            fields.push(NotificationEmbedField {
+                name: "Dotyczy".to_owned(),
+                value: owner,
+                user_supplied: true,
+                timestamp: None,
+            });
+        }
+
+        // Szkolny stores the time a note was added in milliseconds
+        if let Some(added_time) = note.added_date.and_then(|added_date| {
+            OffsetDateTime::from_unix_timestamp_nanos(added_date as i128 * 1_000_000).ok()
+        }) {
+            fields.push(NotificationEmbedField {
+                name: "Dodano".to_owned(),
+                value: added_time.to_string(),
+                user_supplied: false,
+                timestamp: Some(NotificationTimestamp {
+                    time: added_time,
+                    style: TimestampStyle::DateTime,
+                }),
+            });
+        }
+
+        fields.push(NotificationEmbedField {
+            name: "ID".to_owned(),
+            value: note.id.to_string(),
+            user_supplied: false,
+            timestamp: None,
+        });
+
+        NotificationEmbed {
+            kind: PushKind::SharedNote,
+            notification_type: PushKind::SharedNote.type_name().to_owned(),
+            event_type: None,
+            subject_id: None,
+            team_code: Some(share_team_code.to_owned()),
+            profile: None,
+            author: Some(note.shared_by_name.clone()),
+            description: Some(note.body.clone()),
+            code_block: None,
+            fields,
+            // Notes keep the Android ARGB colour, drop the alpha channel
+            color: note.color.map(|color| (color & 0xffffff) as u32),
+            calendar_event: None,
+        }
+    }
+}
+
+mod unshared_note_notification {
+    use super::*;
+
+    pub fn process(unshare_team_code: &str, note_id: &str) -> NotificationEmbed {
+        NotificationEmbed {
+            kind: PushKind::UnsharedNote,
+            notification_type: PushKind::UnsharedNote.type_name().to_owned(),
+            event_type: None,
+            subject_id: None,
+            team_code: Some(unshare_team_code.to_owned()),
+            profile: None,
+            author: None,
+            description: None,
+            code_block: None,
+            fields: vec![
+                NotificationEmbedField {
+                    name: "Grupa".to_owned(),
+                    value: unshare_team_code.to_owned(),
+                    user_supplied: true,
+                    timestamp: None,
+                },
+                NotificationEmbedField {
+                    name: "ID".to_owned(),
+                    value: note_id.to_owned(),
+                    user_supplied: true,
+                    timestamp: None,
+                },
+            ],
+            color: None,
+            calendar_event: None,
+        }
+    }
+}
+
+mod admin_notification {
+    use super::*;
+
+    use crate::szkolny_push::{AppUpdate, PushMessage};
+
+    pub fn process_message(kind: PushKind, message: &PushMessage) -> NotificationEmbed {
+        NotificationEmbed {
+            kind,
+            notification_type: kind.type_name().to_owned(),
+            event_type: None,
+            subject_id: None,
+            team_code: None,
+            profile: None,
+            author: Some("Szkolny.eu".to_owned()),
+            description: (!message.message.is_empty()).then(|| message.message.clone()),
+            code_block: None,
+            fields: vec![],
+            color: None,
+            calendar_event: None,
+        }
+    }
+
+    pub fn process_app_update(update: &AppUpdate) -> NotificationEmbed {
+        let mut fields = vec![NotificationEmbedField {
+            name: "Wersja".to_owned(),
+            value: match update.version_code {
+                Some(version_code) => format!("{} ({})", update.version_name, version_code),
+                None => update.version_name.clone(),
+            },
+            user_supplied: false,
+            timestamp: None,
+        }];
+
+        if let Some(release_type) = &update.release_type {
+            fields.push(NotificationEmbedField {
+                name: "Kanał".to_owned(),
+                value: release_type.clone(),
+                user_supplied: false,
+                timestamp: None,
+            });
+        }
+
+        if let Some(release_date) = &update.release_date {
+            fields.push(NotificationEmbedField {
+                name: "Data wydania".to_owned(),
+                value: release_date.clone(),
+                user_supplied: false,
+                timestamp: None,
+            });
+        }
+
+        fields.push(NotificationEmbedField {
+            name: "Wymagana".to_owned(),
+            value: if update.update_mandatory {
+                "Tak"
+            } else {
+                "Nie"
+            }
+            .to_owned(),
+            user_supplied: false,
+            timestamp: None,
+        });
+
+        if let Some(download_url) = &update.download_url {
+            fields.push(NotificationEmbedField {
+                name: "Pobierz".to_owned(),
+                value: download_url.clone(),
+                user_supplied: false,
+                timestamp: None,
+            });
+        }
+
+        NotificationEmbed {
+            kind: PushKind::AppUpdate,
+            notification_type: PushKind::AppUpdate.type_name().to_owned(),
+            event_type: None,
+            subject_id: None,
+            team_code: None,
+            profile: None,
+            author: Some("Szkolny.eu".to_owned()),
+            description: update.release_notes.clone(),
+            code_block: None,
+            fields,
+            color: None,
+            calendar_event: None,
         }
     }
 }
@@ -168,21 +742,125 @@ mod unshared_event_notification {
 mod other_notification {
     use super::*;
 
-    pub struct OtherNotificationProcessor {}
+    pub fn process(notification: &serde_json::Value) -> NotificationEmbed {
+        NotificationEmbed {
+            kind: PushKind::Unknown,
+            notification_type: notification["type"].as_str().unwrap_or("null").to_owned(),
+            event_type: None,
+            subject_id: None,
+            team_code: None,
+            profile: None,
+            author: None,
+            description: None,
+            code_block: Some(notification.to_string()),
+            fields: vec![],
+            color: None,
+            calendar_event: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    impl NotificationProcessor for OtherNotificationProcessor {
-        fn process(
-            &self,
-            notification: &serde_json::Value,
-            _librus_config: &LibrusConfig,
-        ) -> NotificationEmbed {
-            let embed = NotificationEmbed {
-                author: None,
-                description: Some(format!("```json\n{}\n```", notification.to_string())),
-                fields: vec![],
-            };
+    use crate::test_util;
 
-            embed
+    use time::macros::{date, datetime, time};
+
+    #[test]
+    fn converts_szkolny_dates() {
+        assert_eq!(szkolny_date_convert(20231012), Some(date!(2023 - 10 - 12)));
+    }
+
+    #[test]
+    fn rejects_invalid_szkolny_dates() {
+        assert_eq!(szkolny_date_convert(20261399), None);
+        assert_eq!(szkolny_date_convert(20230230), None);
+        assert_eq!(szkolny_date_convert(u64::MAX), None);
+    }
+
+    #[test]
+    fn rejects_invalid_szkolny_times() {
+        assert_eq!(szkolny_time_convert(80000), Some(time!(8:00)));
+        assert_eq!(szkolny_time_convert(250000), None);
+        assert_eq!(szkolny_time_convert(86000), None);
+        assert_eq!(szkolny_time_convert(u64::MAX), None);
+    }
+
+    #[test]
+    fn moves_times_in_dst_gap_forward() {
+        // Clocks in Warsaw go from 2:00 to 3:00 on 2024-03-31
+        let time =
+            szkolny_datetime_convert(20240331, Some(23000), &test_util::librus_config()).unwrap();
+        assert_eq!(time, datetime!(2024-03-31 3:30 +2));
+
+        let time =
+            szkolny_datetime_convert(20240331, Some(13000), &test_util::librus_config()).unwrap();
+        assert_eq!(time, datetime!(2024-03-31 1:30 +1));
+    }
+
+    fn shared_event(event_date: u64, start_time: Option<u64>) -> ProcessedNotification {
+        test_util::shared_event(serde_json::json!({
+            "eventDate": event_date,
+            "startTime": start_time
+        }))
+    }
+
+    fn field<'a>(embed: &'a NotificationEmbed, name: &str) -> &'a NotificationEmbedField {
+        embed
+            .fields
+            .iter()
+            .find(|field| field.name == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn escapes_unknown_note_owners() {
+        let notification = test_util::process(serde_json::json!({
+            "type": "sharedNote",
+            "title": "2a - Nowa notatka",
+            "message": "Jan Kowalski udostępnił notatkę",
+            "shareTeamCode": "2a",
+            "note": serde_json::json!({
+                "id": 1,
+                "ownerType": "@everyone",
+                "ownerId": 5,
+                "body": "Notatka",
+                "sharedByName": "Jan Kowalski"
+            })
+            .to_string()
+        }));
+        let owner = field(&notification.embed, "Dotyczy");
+
+        assert_eq!(owner.value, "@everyone (#5)");
+        assert!(owner.user_supplied);
+    }
+
+    #[test]
+    fn shows_event_dates() {
+        let notification = shared_event(20231012, Some(80000));
+        let embed = &notification.embed;
+
+        assert_eq!(field(embed, "Data").value, "2023-10-12");
+        assert!(field(embed, "Data").timestamp.is_some());
+        assert_eq!(field(embed, "Godzina").value, "8:00:00.0");
+        assert!(embed.calendar_event.is_some());
+    }
+
+    #[test]
+    fn shows_raw_invalid_event_dates() {
+        for (event_date, start_time) in [(20261399, Some(80000)), (20231012, Some(250000))] {
+            let notification = shared_event(event_date, start_time);
+            let embed = &notification.embed;
+
+            assert!(field(embed, "Data").timestamp.is_none());
+            assert!(field(embed, "Godzina").timestamp.is_none());
+            assert!(embed.calendar_event.is_none());
         }
+
+        let notification = shared_event(20261399, Some(250000));
+        assert_eq!(field(&notification.embed, "Data").value, "20261399");
+        assert_eq!(field(&notification.embed, "Godzina").value, "250000");
     }
 }
@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::{event_types::EventTypeConfig, notification_types::IdKind, LibrusConfig};
+
+// Names for one Szkolny profile. Subject and teacher IDs only make sense within a single
+// e-register, so classes on different registers each get their own profile. IDs missing from a
+// profile are looked up in the main section.
+#[derive(Deserialize)]
+pub struct ProfileConfig {
+    pub name: String,
+    // Team codes of the profile, e.g. "PL_LIBRUS_12345:2a"
+    #[serde(default)]
+    teams: Vec<String>,
+    // Used for notifications without a team code, matches when the title contains one of these
+    #[serde(default)]
+    titles: Vec<String>,
+    #[serde(default)]
+    pub subjects: HashMap<String, String>,
+    #[serde(default)]
+    pub teachers: HashMap<String, String>,
+    #[serde(default)]
+    pub event_types: HashMap<String, EventTypeConfig>,
+}
+
+impl ProfileConfig {
+    fn matches(&self, title: &str, team_code: Option<&str>) -> bool {
+        if let Some(team_code) = team_code {
+            if self.teams.iter().any(|team| team == team_code) {
+                return true;
+            }
+        }
+
+        self.titles
+            .iter()
+            .any(|profile_title| title.contains(profile_title.as_str()))
+    }
+}
+
+pub fn find_profile<'a>(
+    librus_config: &'a LibrusConfig,
+    title: &str,
+    team_code: Option<&str>,
+) -> Option<&'a ProfileConfig> {
+    librus_config
+        .profiles
+        .iter()
+        .find(|profile| profile.matches(title, team_code))
+}
+
+pub fn get_profile<'a>(librus_config: &'a LibrusConfig, name: &str) -> Option<&'a ProfileConfig> {
+    librus_config
+        .profiles
+        .iter()
+        .find(|profile| profile.name == name)
+}
+
+// Names from the profile take precedence over the ones from the main section
+pub fn find_name<'a>(
+    librus_config: &'a LibrusConfig,
+    profile: Option<&'a ProfileConfig>,
+    kind: IdKind,
+    id: &str,
+) -> Option<&'a String> {
+    let (profile_names, names) = match kind {
+        IdKind::Subject => (profile.map(|p| &p.subjects), &librus_config.subjects),
+        IdKind::Teacher => (profile.map(|p| &p.teachers), &librus_config.teachers),
+    };
+
+    profile_names
+        .and_then(|profile_names| profile_names.get(id))
+        .or_else(|| names.get(id))
+}
+
+// The profile name stored in the database, the main section uses an empty one
+pub fn profile_key(profile: Option<&ProfileConfig>) -> &str {
+    profile.map(|profile| profile.name.as_str()).unwrap_or("")
+}
@@ -0,0 +1,119 @@
+const ZERO_WIDTH_SPACE: char = '\u{200b}';
+
+const MARKDOWN_CHARS: &[char] = &['\\', '*', '_', '~', '`', '|', '[', ']', '(', ')', '<', '>'];
+const LINE_START_CHARS: &[char] = &['#', '-', '+'];
+
+const INVITE_HOSTS: &[&str] = &[
+    "discord.gg/",
+    "discord.com/invite/",
+    "discordapp.com/invite/",
+];
+
+// Text that came from other Szkolny users (topics, names, messages) goes through here before it
+// ends up in a message. Text from the config is trusted and is never passed to these functions.
+pub fn escape_markdown(text: &str) -> String {
+    let text = neutralize(text);
+    let mut escaped = String::with_capacity(text.len());
+
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            escaped.push('\n');
+        }
+
+        let content = line.trim_start();
+        escaped.push_str(&line[..line.len() - content.len()]);
+
+        if content.starts_with(LINE_START_CHARS) {
+            escaped.push('\\');
+        }
+
+        for c in content.chars() {
+            if MARKDOWN_CHARS.contains(&c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+    }
+
+    escaped
+}
+
+// For places where Discord does not render markdown (embed author, footer), but where mentions
+// and invite links should still not be usable.
+pub fn neutralize(text: &str) -> String {
+    let mut neutralized = text.replace('@', &format!("@{}", ZERO_WIDTH_SPACE));
+
+    for host in INVITE_HOSTS {
+        let (name, rest) = host.split_once('.').unwrap();
+        let broken = format!("{}{}.{}", name, ZERO_WIDTH_SPACE, rest);
+        neutralized = replace_ignore_ascii_case(&neutralized, host, &broken);
+    }
+
+    neutralized
+}
+
+fn replace_ignore_ascii_case(text: &str, from: &str, to: &str) -> String {
+    let lowercase = text.to_ascii_lowercase();
+    let mut replaced = String::with_capacity(text.len());
+    let mut last = 0;
+
+    for (start, _) in lowercase.match_indices(from) {
+        replaced.push_str(&text[last..start]);
+        replaced.push_str(to);
+        last = start + from.len();
+    }
+    replaced.push_str(&text[last..]);
+
+    replaced
+}
+
+pub fn escape_code_block(text: &str) -> String {
+    neutralize(text).replace('`', &format!("`{}", ZERO_WIDTH_SPACE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_markdown() {
+        assert_eq!(
+            escape_markdown("*bold* _it_ [link](x) a|b"),
+            "\\*bold\\* \\_it\\_ \\[link\\]\\(x\\) a\\|b"
+        );
+    }
+
+    #[test]
+    fn escapes_line_starts() {
+        assert_eq!(
+            escape_markdown("# title\n  - item\nx - y"),
+            "\\# title\n  \\- item\nx - y"
+        );
+    }
+
+    #[test]
+    fn neutralizes_mentions() {
+        assert_eq!(neutralize("@everyone"), "@\u{200b}everyone");
+        assert_eq!(neutralize("a@b"), "a@\u{200b}b");
+    }
+
+    #[test]
+    fn neutralizes_invites_in_any_case() {
+        assert_eq!(neutralize("discord.gg/abc"), "discord\u{200b}.gg/abc");
+        assert_eq!(neutralize("DISCORD.GG/abc"), "discord\u{200b}.gg/abc");
+        assert_eq!(
+            neutralize("https://Discord.com/invite/abc"),
+            "https://discord\u{200b}.com/invite/abc"
+        );
+        assert_eq!(neutralize("discord.com/channels"), "discord.com/channels");
+    }
+
+    #[test]
+    fn escapes_code_block() {
+        assert_eq!(
+            escape_code_block("```end"),
+            "`\u{200b}`\u{200b}`\u{200b}end"
+        );
+        assert_eq!(escape_code_block("@here"), "@\u{200b}here");
+    }
+}
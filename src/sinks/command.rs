@@ -0,0 +1,200 @@
+use std::{collections::HashMap, error::Error, process::Stdio, time::Duration};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::{io::AsyncWriteExt, process::Command};
+
+use super::{document::NotificationDocument, DeliveryError, Sink, SinkContext};
+use crate::notification_types::ProcessedNotification;
+
+const ENV_PREFIX: &str = "SZKOLNY_";
+
+#[derive(Deserialize)]
+pub struct CommandConfig {
+    program: String,
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default = "default_timeout")]
+    timeout_secs: u64,
+}
+
+fn default_timeout() -> u64 {
+    30
+}
+
+#[derive(Serialize)]
+pub struct CommandMessage {
+    env: Vec<(String, String)>,
+    stdin: String,
+}
+
+pub struct CommandSink {
+    config: CommandConfig,
+}
+
+impl CommandSink {
+    pub fn new(config: CommandConfig) -> Self {
+        CommandSink { config }
+    }
+}
+
+#[async_trait(?Send)]
+impl Sink for CommandSink {
+    type Message = CommandMessage;
+
+    fn render(
+        &self,
+        notification: &ProcessedNotification,
+        _context: &SinkContext<'_>,
+    ) -> Result<Option<CommandMessage>, Box<dyn Error>> {
+        let embed = &notification.embed;
+
+        let mut env = HashMap::from([
+            ("ID".to_owned(), notification.id.clone()),
+            ("TYPE".to_owned(), embed.notification_type.clone()),
+            ("TITLE".to_owned(), notification.title.clone()),
+            ("MESSAGE".to_owned(), notification.message.clone()),
+        ]);
+
+        let optional = [
+            ("AUTHOR", embed.author.clone()),
+            ("DESCRIPTION", embed.description.clone()),
+            ("TEAM", embed.team_code.clone()),
+            ("SUBJECT_ID", embed.subject_id.map(|id| id.to_string())),
+            ("EVENT_TYPE", embed.event_type.map(|id| id.to_string())),
+        ];
+        for (name, value) in optional {
+            if let Some(value) = value {
+                env.insert(name.to_owned(), value);
+            }
+        }
+
+        for field in &embed.fields {
+            env.insert(
+                format!("FIELD_{}", env_name(&field.name)),
+                field.value.clone(),
+            );
+        }
+
+        let mut env: Vec<(String, String)> = env
+            .into_iter()
+            .map(|(name, value)| (format!("{}{}", ENV_PREFIX, name), value))
+            .collect();
+        env.sort();
+
+        Ok(Some(CommandMessage {
+            env,
+            stdin: serde_json::to_string(&NotificationDocument::new(notification))?,
+        }))
+    }
+
+    async fn deliver(
+        &self,
+        message: &CommandMessage,
+        _context: &SinkContext<'_>,
+    ) -> Result<(), DeliveryError> {
+        let mut child = Command::new(&self.config.program)
+            .args(&self.config.args)
+            .envs(message.env.iter().map(|(name, value)| (name, value)))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| DeliveryError::Permanent(e.into()))?;
+
+        let mut stdin = child.stdin.take().unwrap();
+        let stdin_json = message.stdin.clone();
+
+        let run = async move {
+            // A script that doesn't read its input shouldn't count as a failure
+            let _ = stdin.write_all(stdin_json.as_bytes()).await;
+            drop(stdin);
+            child.wait_with_output().await
+        };
+
+        let output = tokio::time::timeout(Duration::from_secs(self.config.timeout_secs), run)
+            .await
+            .map_err(|_| retryable(format!("{} timed out", self.config.program)))?
+            .map_err(|e| retryable(e.to_string()))?;
+
+        // Only logged, stdout of the bot can be taken by a JSONL output
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            eprintln!("  -> {}: {}", self.config.program, line);
+        }
+
+        if !output.status.success() {
+            return Err(retryable(format!(
+                "{} exited with {}: {}",
+                self.config.program,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+fn retryable(error: String) -> DeliveryError {
+    DeliveryError::Retryable {
+        error: error.into(),
+        retry_after: None,
+    }
+}
+
+// Field names are Polish, so anything outside A-Z and digits is replaced
+fn env_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c.to_ascii_uppercase() {
+            c @ ('A'..='Z' | '0'..='9') => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    use crate::test_util;
+
+    async fn run(script: &str) -> Result<(), DeliveryError> {
+        let sink = CommandSink::new(CommandConfig {
+            program: "sh".to_owned(),
+            args: vec!["-c".to_owned(), script.to_owned()],
+            timeout_secs: 5,
+        });
+        let librus_config = test_util::librus_config();
+        let context = SinkContext {
+            client: &reqwest::Client::new(),
+            database: &rusqlite::Connection::open_in_memory().unwrap(),
+            librus_config: &librus_config,
+        };
+
+        let message = sink
+            .render(&test_util::shared_event(json!({})), &context)
+            .unwrap()
+            .unwrap();
+        sink.deliver(&message, &context).await
+    }
+
+    #[tokio::test]
+    async fn passes_the_notification() {
+        run(r#"grep -q '"type":"sharedEvent"' && [ "$SZKOLNY_TYPE" = sharedEvent ] && echo ok"#)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn retries_failed_commands() {
+        let result = run("echo broken >&2; exit 1").await;
+
+        let Err(DeliveryError::Retryable { error, .. }) = result else {
+            panic!("not a retryable error");
+        };
+        assert!(error.to_string().ends_with("exit status: 1: broken"));
+    }
+}
@@ -0,0 +1,578 @@
+use std::error::Error;
+
+use async_trait::async_trait;
+use discord_message::DiscordMessage;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::{DeliveryError, Sink, SinkContext};
+use crate::{
+    db,
+    notification_filter::NotificationFilter,
+    notification_types::{
+        IdKind, NotificationEmbed, NotificationTimestamp, ProcessedNotification, TimestampStyle,
+    },
+    profiles, sanitize, LibrusConfig,
+};
+
+const DEFAULT_COLOR: u32 = 0x02a0e9;
+const DEFAULT_THREAD_NAME: &str = "Inne";
+const MAX_THREAD_NAME_LENGTH: usize = 100;
+
+#[derive(Deserialize)]
+pub struct ColorRule {
+    #[serde(flatten)]
+    filter: NotificationFilter,
+    color: u32,
+}
+
+#[derive(Deserialize)]
+pub struct MentionRule {
+    #[serde(flatten)]
+    filter: NotificationFilter,
+    #[serde(default)]
+    roles: Vec<String>,
+    #[serde(default)]
+    users: Vec<String>,
+}
+
+#[derive(Serialize, Default)]
+struct AllowedMentions {
+    parse: Vec<String>,
+    roles: Vec<String>,
+    users: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct DiscordConfig {
+    webhook_url: String,
+    username: Option<String>,
+    avatar_url: Option<Url>,
+    color: Option<u32>,
+    thread_id: Option<String>,
+    #[serde(default)]
+    forum: bool,
+    thread_name: Option<String>,
+    #[serde(default)]
+    calendar_attachments: bool,
+    #[serde(default)]
+    colors: Vec<ColorRule>,
+    #[serde(default)]
+    mentions: Vec<MentionRule>,
+}
+
+#[derive(Serialize)]
+pub struct WebhookPayload {
+    #[serde(flatten)]
+    message: DiscordMessage,
+    allowed_mentions: AllowedMentions,
+    #[serde(skip)]
+    attachment: Option<Attachment>,
+    #[serde(skip)]
+    forum_thread: Option<ForumThread>,
+}
+
+struct Attachment {
+    file_name: String,
+    content_type: String,
+    data: Vec<u8>,
+}
+
+#[derive(Deserialize)]
+struct WebhookMessageResponse {
+    channel_id: String,
+}
+
+struct ForumThread {
+    key: String,
+    name: String,
+}
+
+pub struct DiscordSink {
+    config: DiscordConfig,
+}
+
+impl DiscordSink {
+    pub fn new(config: DiscordConfig) -> Self {
+        DiscordSink { config }
+    }
+}
+
+#[async_trait(?Send)]
+impl Sink for DiscordSink {
+    type Message = WebhookPayload;
+
+    fn render(
+        &self,
+        notification: &ProcessedNotification,
+        context: &SinkContext<'_>,
+    ) -> Result<Option<WebhookPayload>, Box<dyn Error>> {
+        let discord_config = &self.config;
+        let embed = &notification.embed;
+
+        let color = embed_color(embed, discord_config);
+        let mentions = embed_mentions(embed, discord_config);
+        let forum_thread = discord_config
+            .forum
+            .then(|| forum_thread(embed, discord_config, context.librus_config));
+        let attachment = embed
+            .calendar_event
+            .as_ref()
+            .filter(|_| discord_config.calendar_attachments)
+            .map(|calendar_event| Attachment {
+                file_name: calendar_event.file_name(),
+                content_type: "text/calendar; charset=utf-8".to_owned(),
+                data: calendar_event.to_ics().into_bytes(),
+            });
+
+        let description = match (&embed.description, &embed.code_block) {
+            (Some(description), _) => sanitize::escape_markdown(description),
+            (None, Some(code)) => format!("```json\n{}\n```", sanitize::escape_code_block(code)),
+            (None, None) => "".to_owned(),
+        };
+
+        let discord_message = DiscordMessage {
+            avatar_url: discord_config.avatar_url.clone(),
+            username: discord_config.username.clone(),
+            content: mentions_content(&mentions),
+            embeds: vec![discord_message::Embed {
+                author: embed
+                    .author
+                    .as_ref()
+                    .map(|author| discord_message::EmbedAuthor {
+                        name: sanitize::neutralize(author),
+                        url: None,
+                        icon_url: None,
+                    }),
+
+                title: sanitize::escape_markdown(&notification.message),
+                description,
+                color: Some(color),
+                footer: Some(discord_message::EmbedFooter {
+                    text: format!(
+                        "{} / {}",
+                        sanitize::neutralize(&notification.title),
+                        sanitize::neutralize(&embed.notification_type)
+                    ),
+                    icon_url: Some(Url::parse("https://szkolny.eu/images/logo.png").unwrap()),
+                }),
+                fields: Some(
+                    embed
+                        .fields
+                        .iter()
+                        .map(|field| discord_message::EmbedField {
+                            title: field.name.clone(),
+                            value: match &field.timestamp {
+                                Some(timestamp) => discord_timestamp(timestamp),
+                                None if field.user_supplied => {
+                                    sanitize::escape_markdown(&field.value)
+                                }
+                                None => field.value.clone(),
+                            },
+                            inline: true,
+                        })
+                        .collect(),
+                ),
+                ..Default::default()
+            }],
+        };
+
+        Ok(Some(WebhookPayload {
+            message: discord_message,
+            allowed_mentions: mentions,
+            attachment,
+            forum_thread,
+        }))
+    }
+
+    async fn deliver(
+        &self,
+        payload: &WebhookPayload,
+        context: &SinkContext<'_>,
+    ) -> Result<(), DeliveryError> {
+        let client = context.client;
+        let database = context.database;
+
+        let mut webhook_url =
+            Url::parse(&self.config.webhook_url).map_err(|e| DeliveryError::Permanent(e.into()))?;
+
+        if let Some(thread_id) = &self.config.thread_id {
+            webhook_url
+                .query_pairs_mut()
+                .append_pair("thread_id", thread_id);
+        } else if let Some(forum_thread) = &payload.forum_thread {
+            let webhook_id = webhook_id(&webhook_url);
+
+            if let Some(thread_id) = db::get_thread(database, &webhook_id, &forum_thread.key)? {
+                let mut thread_url = webhook_url.clone();
+                thread_url
+                    .query_pairs_mut()
+                    .append_pair("thread_id", &thread_id);
+
+                match send_message(payload, None, thread_url, client).await {
+                    Ok(_) => return Ok(()),
+                    Err(e) if e.status() == Some(StatusCode::NOT_FOUND) => {
+                        log!(
+                            "  -> Thread {} ({}) no longer exists, creating a new one...",
+                            thread_id,
+                            forum_thread.name
+                        );
+                        db::remove_thread(database, &webhook_id, &forum_thread.key)?;
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            }
+
+            webhook_url.query_pairs_mut().append_pair("wait", "true");
+
+            let response: WebhookMessageResponse =
+                send_message(payload, Some(&forum_thread.name), webhook_url, client)
+                    .await?
+                    .json()
+                    .await?;
+
+            db::set_thread(
+                database,
+                &webhook_id,
+                &forum_thread.key,
+                &response.channel_id,
+            )?;
+
+            return Ok(());
+        }
+
+        send_message(payload, None, webhook_url, client).await?;
+
+        Ok(())
+    }
+
+    // Shows the payload of the first request `deliver` would make
+    fn preview(
+        &self,
+        payload: &WebhookPayload,
+        context: &SinkContext<'_>,
+    ) -> Result<serde_json::Value, Box<dyn Error>> {
+        let thread_id = match (&self.config.thread_id, &payload.forum_thread) {
+            (Some(thread_id), _) => Some(thread_id.clone()),
+            (None, Some(forum_thread)) => db::get_thread(
+                context.database,
+                &webhook_id(&Url::parse(&self.config.webhook_url)?),
+                &forum_thread.key,
+            )?,
+            (None, None) => None,
+        };
+
+        Ok(match thread_id {
+            Some(thread_id) => {
+                // Sent in the URL rather than in the payload
+                let mut payload_json = payload_json(payload, None);
+                payload_json["thread_id"] = thread_id.into();
+                payload_json
+            }
+            None => payload_json(
+                payload,
+                payload
+                    .forum_thread
+                    .as_ref()
+                    .map(|forum_thread| forum_thread.name.as_str()),
+            ),
+        })
+    }
+}
+
+fn forum_thread(
+    embed: &NotificationEmbed,
+    discord_config: &DiscordConfig,
+    librus_config: &LibrusConfig,
+) -> ForumThread {
+    match embed.subject_id {
+        // Subject IDs of different profiles can collide, so they get separate threads
+        Some(subject_id) => ForumThread {
+            key: match &embed.profile {
+                Some(profile) => format!("subject:{}:{}", profile, subject_id),
+                None => format!("subject:{}", subject_id),
+            },
+            name: profiles::find_name(
+                librus_config,
+                embed
+                    .profile
+                    .as_deref()
+                    .and_then(|profile| profiles::get_profile(librus_config, profile)),
+                IdKind::Subject,
+                &subject_id.to_string(),
+            )
+            .cloned()
+            .unwrap_or_else(|| subject_id.to_string())
+            .chars()
+            .take(MAX_THREAD_NAME_LENGTH)
+            .collect(),
+        },
+        None => ForumThread {
+            key: "default".to_owned(),
+            name: discord_config
+                .thread_name
+                .clone()
+                .unwrap_or_else(|| DEFAULT_THREAD_NAME.to_owned()),
+        },
+    }
+}
+
+// Webhook URLs look like https://discord.com/api/webhooks/<id>/<token>, only the ID is stored
+fn webhook_id(webhook_url: &Url) -> String {
+    webhook_url
+        .path_segments()
+        .and_then(|mut segments| {
+            segments.find(|segment| *segment == "webhooks")?;
+            segments.next()
+        })
+        .unwrap_or(webhook_url.as_str())
+        .to_owned()
+}
+
+fn discord_timestamp(timestamp: &NotificationTimestamp) -> String {
+    let unix = timestamp.time.unix_timestamp();
+
+    match timestamp.style {
+        TimestampStyle::Date => format!("<t:{}:D> (<t:{}:R>)", unix, unix),
+        TimestampStyle::DateTime => format!("<t:{}:F> (<t:{}:R>)", unix, unix),
+        TimestampStyle::Time => format!("<t:{}:t>", unix),
+    }
+}
+
+// The event type colour is only a fallback for webhooks without a colour of their own
+fn embed_color(embed: &NotificationEmbed, discord_config: &DiscordConfig) -> u32 {
+    discord_config
+        .colors
+        .iter()
+        .find(|rule| rule.filter.matches(embed))
+        .map(|rule| rule.color)
+        .or(discord_config.color)
+        .or(embed.color)
+        .unwrap_or(DEFAULT_COLOR)
+}
+
+fn embed_mentions(embed: &NotificationEmbed, discord_config: &DiscordConfig) -> AllowedMentions {
+    let mut mentions = AllowedMentions::default();
+
+    for rule in discord_config
+        .mentions
+        .iter()
+        .filter(|rule| rule.filter.matches(embed))
+    {
+        for role in &rule.roles {
+            if !mentions.roles.contains(role) {
+                mentions.roles.push(role.clone());
+            }
+        }
+        for user in &rule.users {
+            if !mentions.users.contains(user) {
+                mentions.users.push(user.clone());
+            }
+        }
+    }
+
+    mentions
+}
+
+fn mentions_content(mentions: &AllowedMentions) -> String {
+    mentions
+        .roles
+        .iter()
+        .map(|role| format!("<@&{}>", role))
+        .chain(mentions.users.iter().map(|user| format!("<@{}>", user)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn payload_json(message: &WebhookPayload, thread_name: Option<&str>) -> serde_json::Value {
+    let mut payload_json = serde_json::to_value(message).unwrap();
+    if let Some(thread_name) = thread_name {
+        payload_json["thread_name"] = thread_name.into();
+    }
+    if let Some(attachment) = &message.attachment {
+        payload_json["attachments"] =
+            serde_json::json!([{ "id": 0, "filename": attachment.file_name }]);
+    }
+
+    payload_json
+}
+
+async fn send_message(
+    message: &WebhookPayload,
+    thread_name: Option<&str>,
+    webhook_url: Url,
+    client: &reqwest::Client,
+) -> Result<reqwest::Response, reqwest::Error> {
+    let payload_json = payload_json(message, thread_name);
+
+    let request = client.post(webhook_url);
+    let request = match &message.attachment {
+        Some(attachment) => request.multipart(
+            reqwest::multipart::Form::new()
+                .text("payload_json", payload_json.to_string())
+                .part(
+                    "files[0]",
+                    reqwest::multipart::Part::bytes(attachment.data.clone())
+                        .file_name(attachment.file_name.clone())
+                        .mime_str(&attachment.content_type)?,
+                ),
+        ),
+        None => request
+            .header("Content-Type", "application/json")
+            .body(payload_json.to_string()),
+    };
+
+    request.send().await?.error_for_status()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::{json, Value};
+
+    use crate::test_util;
+
+    fn shared_event(topic: &str, event_type: i32) -> ProcessedNotification {
+        test_util::shared_event(json!({
+            "type": event_type,
+            "topic": topic,
+            "sharedByName": "@everyone"
+        }))
+    }
+
+    fn render(config: &str, notification: &ProcessedNotification) -> Value {
+        let sink = DiscordSink::new(toml::from_str(config).unwrap());
+        let librus_config = test_util::librus_config();
+        let context = SinkContext {
+            client: &reqwest::Client::new(),
+            database: &rusqlite::Connection::open_in_memory().unwrap(),
+            librus_config: &librus_config,
+        };
+
+        let payload = sink.render(notification, &context).unwrap().unwrap();
+        serde_json::to_value(payload).unwrap()
+    }
+
+    const WEBHOOK_URL: &str = r#"webhook_url = "https://discord.com/api/webhooks/1/token""#;
+
+    #[test]
+    fn allows_no_mentions_by_default() {
+        let payload = render(WEBHOOK_URL, &shared_event("@everyone @here", 1));
+
+        assert_eq!(
+            payload["allowed_mentions"],
+            json!({ "parse": [], "roles": [], "users": [] })
+        );
+        assert_eq!(payload["content"], "");
+    }
+
+    #[test]
+    fn allows_only_configured_mentions() {
+        let config = format!(
+            r#"
+            {}
+
+            [[mentions]]
+            event_type = 1
+            roles = ["111"]
+            users = ["222"]
+
+            [[mentions]]
+            event_type = 2
+            roles = ["333"]
+            "#,
+            WEBHOOK_URL
+        );
+        let payload = render(&config, &shared_event("Sprawdzian", 1));
+
+        assert_eq!(
+            payload["allowed_mentions"],
+            json!({ "parse": [], "roles": ["111"], "users": ["222"] })
+        );
+        assert_eq!(payload["content"], "<@&111> <@222>");
+    }
+
+    #[test]
+    fn neutralizes_mentions_from_users() {
+        let payload = render(WEBHOOK_URL, &shared_event("@everyone test", 1));
+        let embed = &payload["embeds"][0];
+
+        assert_eq!(embed["description"], "@\u{200b}everyone test");
+        assert_eq!(embed["author"]["name"], "@\u{200b}everyone");
+        assert!(!payload.to_string().contains("@everyone"));
+    }
+
+    #[test]
+    fn prefers_color_rules() {
+        let config = format!(
+            r#"
+            {}
+            color = 0x111111
+
+            [[colors]]
+            event_type = 2
+            color = 0x222222
+            "#,
+            WEBHOOK_URL
+        );
+
+        let payload = render(&config, &shared_event("Kartkówka", 2));
+        assert_eq!(payload["embeds"][0]["color"], 0x222222);
+
+        let payload = render(&config, &shared_event("Sprawdzian", 1));
+        assert_eq!(payload["embeds"][0]["color"], 0x111111);
+    }
+
+    #[test]
+    fn falls_back_to_event_type_color() {
+        let payload = render(WEBHOOK_URL, &shared_event("Sprawdzian", 1));
+        assert_eq!(payload["embeds"][0]["color"], 0xf44336);
+
+        let payload = render(WEBHOOK_URL, &shared_event("Inne", 100));
+        assert_eq!(payload["embeds"][0]["color"], DEFAULT_COLOR);
+    }
+
+    fn preview(config: &str, database: &rusqlite::Connection) -> Value {
+        let sink = DiscordSink::new(toml::from_str(config).unwrap());
+        let librus_config = test_util::librus_config();
+        let context = SinkContext {
+            client: &reqwest::Client::new(),
+            database,
+            librus_config: &librus_config,
+        };
+
+        let payload = sink
+            .render(&shared_event("Sprawdzian", 1), &context)
+            .unwrap()
+            .unwrap();
+        sink.preview(&payload, &context).unwrap()
+    }
+
+    #[test]
+    fn previews_forum_threads_and_attachments() {
+        let database = db::connect(":memory:".into()).unwrap();
+        let config = format!(
+            r#"
+            {}
+            forum = true
+            calendar_attachments = true
+            "#,
+            WEBHOOK_URL
+        );
+
+        let payload = preview(&config, &database);
+        assert_eq!(payload["thread_name"], "Matematyka");
+        assert!(payload.get("thread_id").is_none());
+        assert_eq!(
+            payload["attachments"],
+            json!([{ "id": 0, "filename": "event-1.ics" }])
+        );
+
+        db::set_thread(&database, "1", "subject:123", "999").unwrap();
+        let payload = preview(&config, &database);
+        assert_eq!(payload["thread_id"], "999");
+        assert!(payload.get("thread_name").is_none());
+    }
+}
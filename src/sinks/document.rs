@@ -0,0 +1,63 @@
+use serde::Serialize;
+use time::format_description::well_known::Rfc3339;
+
+use crate::notification_types::ProcessedNotification;
+
+// Bump whenever a field is removed or changes meaning, receivers rely on this layout
+const DOCUMENT_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+pub struct NotificationDocument {
+    version: u32,
+    id: String,
+    #[serde(rename = "type")]
+    notification_type: String,
+    title: String,
+    message: String,
+    event_type: Option<i32>,
+    subject_id: Option<i32>,
+    team_code: Option<String>,
+    author: Option<String>,
+    description: Option<String>,
+    fields: Vec<DocumentField>,
+    payload: serde_json::Value,
+}
+
+#[derive(Serialize)]
+struct DocumentField {
+    name: String,
+    value: String,
+    timestamp: Option<String>,
+}
+
+impl NotificationDocument {
+    pub fn new(notification: &ProcessedNotification) -> Self {
+        let embed = &notification.embed;
+
+        NotificationDocument {
+            version: DOCUMENT_VERSION,
+            id: notification.id.clone(),
+            notification_type: embed.notification_type.clone(),
+            title: notification.title.clone(),
+            message: notification.message.clone(),
+            event_type: embed.event_type,
+            subject_id: embed.subject_id,
+            team_code: embed.team_code.clone(),
+            author: embed.author.clone(),
+            description: embed.description.clone(),
+            fields: embed
+                .fields
+                .iter()
+                .map(|field| DocumentField {
+                    name: field.name.clone(),
+                    value: field.value.clone(),
+                    timestamp: field
+                        .timestamp
+                        .as_ref()
+                        .map(|timestamp| timestamp.time.format(&Rfc3339).unwrap()),
+                })
+                .collect(),
+            payload: notification.payload.clone(),
+        }
+    }
+}
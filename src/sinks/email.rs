@@ -0,0 +1,317 @@
+use std::{collections::BTreeMap, error::Error};
+
+use async_trait::async_trait;
+use lettre::{
+    message::{Mailbox, MultiPart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use super::{text, DeliveryError, Sink, SinkContext};
+use crate::{
+    db, notification_filter::NotificationFilter, notification_types::ProcessedNotification,
+};
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+enum SmtpTls {
+    #[default]
+    Starttls,
+    Implicit,
+    None,
+}
+
+#[derive(Deserialize, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum EmailMode {
+    #[default]
+    Instant,
+    Digest,
+}
+
+#[derive(Deserialize)]
+pub struct RecipientRule {
+    #[serde(flatten)]
+    filter: NotificationFilter,
+    to: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct EmailConfig {
+    smtp_host: String,
+    smtp_port: Option<u16>,
+    #[serde(default)]
+    tls: SmtpTls,
+    username: Option<String>,
+    password: Option<String>,
+    from: Mailbox,
+    #[serde(default)]
+    to: Vec<String>,
+    #[serde(default)]
+    recipients: Vec<RecipientRule>,
+    #[serde(default)]
+    mode: EmailMode,
+    #[serde(default = "default_digest_interval")]
+    digest_interval_mins: i64,
+}
+
+fn default_digest_interval() -> i64 {
+    60
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct EmailMessage {
+    recipients: Vec<String>,
+    subject: String,
+    text: String,
+    html: String,
+}
+
+pub struct EmailSink {
+    name: String,
+    config: EmailConfig,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl EmailSink {
+    pub fn new(name: &str, config: EmailConfig) -> Self {
+        let mut builder = match config.tls {
+            SmtpTls::Starttls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host).unwrap()
+            }
+            SmtpTls::Implicit => {
+                AsyncSmtpTransport::<Tokio1Executor>::relay(&config.smtp_host).unwrap()
+            }
+            SmtpTls::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.smtp_host)
+            }
+        };
+
+        if let Some(port) = config.smtp_port {
+            builder = builder.port(port);
+        }
+        match (&config.username, &config.password) {
+            (Some(username), Some(password)) => {
+                builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+            }
+            (None, None) => {}
+            _ => panic!("{}: username and password have to be set together", name),
+        }
+
+        EmailSink {
+            name: name.to_owned(),
+            transport: builder.build(),
+            config,
+        }
+    }
+
+    async fn send_email(
+        &self,
+        recipients: &[String],
+        subject: &str,
+        text: String,
+        html: String,
+    ) -> Result<(), DeliveryError> {
+        let mut builder = Message::builder()
+            .from(self.config.from.clone())
+            .subject(subject);
+
+        for recipient in recipients {
+            builder = builder.to(recipient
+                .parse()
+                .map_err(|e: lettre::address::AddressError| DeliveryError::Permanent(e.into()))?);
+        }
+
+        let email = builder
+            .multipart(MultiPart::alternative_plain_html(text, wrap_html(&html)))
+            .map_err(|e| DeliveryError::Permanent(e.into()))?;
+
+        self.transport.send(email).await?;
+
+        Ok(())
+    }
+}
+
+impl From<lettre::transport::smtp::Error> for DeliveryError {
+    fn from(error: lettre::transport::smtp::Error) -> Self {
+        if error.is_permanent() {
+            DeliveryError::Permanent(error.into())
+        } else {
+            DeliveryError::Retryable {
+                error: error.into(),
+                retry_after: None,
+            }
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl Sink for EmailSink {
+    type Message = EmailMessage;
+
+    fn render(
+        &self,
+        notification: &ProcessedNotification,
+        _context: &SinkContext<'_>,
+    ) -> Result<Option<EmailMessage>, Box<dyn Error>> {
+        let mut recipients = self.config.to.clone();
+        for rule in self
+            .config
+            .recipients
+            .iter()
+            .filter(|rule| rule.filter.matches(&notification.embed))
+        {
+            for recipient in &rule.to {
+                if !recipients.contains(recipient) {
+                    recipients.push(recipient.clone());
+                }
+            }
+        }
+
+        if recipients.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(EmailMessage {
+            recipients,
+            subject: format!("[{}] {}", notification.title, notification.message),
+            text: text::plain_text(notification),
+            html: text::html(notification),
+        }))
+    }
+
+    async fn deliver(
+        &self,
+        message: &EmailMessage,
+        context: &SinkContext<'_>,
+    ) -> Result<(), DeliveryError> {
+        if self.config.mode == EmailMode::Digest {
+            for recipient in &message.recipients {
+                db::add_digest_entry(context.database, &self.name, recipient, message)?;
+            }
+            return Ok(());
+        }
+
+        self.send_email(
+            &message.recipients,
+            &message.subject,
+            message.text.clone(),
+            message.html.clone(),
+        )
+        .await
+    }
+
+    async fn tick(&self, context: &SinkContext<'_>) -> Result<(), DeliveryError> {
+        if self.config.mode != EmailMode::Digest {
+            return Ok(());
+        }
+
+        let entries: Vec<db::DigestEntry<EmailMessage>> =
+            db::get_digest_entries(context.database, &self.name)?;
+
+        let oldest = match entries.iter().map(|entry| entry.created_at).min() {
+            Some(oldest) => oldest,
+            None => return Ok(()),
+        };
+        if OffsetDateTime::now_utc().unix_timestamp() - oldest
+            < self.config.digest_interval_mins * 60
+        {
+            return Ok(());
+        }
+
+        let mut by_recipient: BTreeMap<&str, Vec<&db::DigestEntry<EmailMessage>>> = BTreeMap::new();
+        for entry in &entries {
+            by_recipient
+                .entry(&entry.recipient)
+                .or_default()
+                .push(entry);
+        }
+
+        for (recipient, entries) in by_recipient {
+            let subject = format!("Szkolny.eu: {}", new_notifications(entries.len()));
+            let text = entries
+                .iter()
+                .map(|entry| entry.data.text.as_str())
+                .collect::<Vec<_>>()
+                .join("\n\n----------\n\n");
+            let html = entries
+                .iter()
+                .map(|entry| entry.data.html.as_str())
+                .collect::<Vec<_>>()
+                .join("<hr>");
+
+            self.send_email(&[recipient.to_owned()], &subject, text, html)
+                .await?;
+
+            let ids: Vec<i64> = entries.iter().map(|entry| entry.id).collect();
+            db::remove_digest_entries(context.database, &ids)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Polish plural forms: 1 nowe powiadomienie, 2-4 nowe powiadomienia, 5 nowych powiadomień
+fn new_notifications(count: usize) -> String {
+    let form = if count == 1 {
+        "nowe powiadomienie"
+    } else if (2..=4).contains(&(count % 10)) && !(12..=14).contains(&(count % 100)) {
+        "nowe powiadomienia"
+    } else {
+        "nowych powiadomień"
+    };
+
+    format!("{} {}", count, form)
+}
+
+fn wrap_html(html: &str) -> String {
+    format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"></head><body>{}</body></html>",
+        html
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_new_notifications_in_polish() {
+        assert_eq!(new_notifications(1), "1 nowe powiadomienie");
+        assert_eq!(new_notifications(2), "2 nowe powiadomienia");
+        assert_eq!(new_notifications(4), "4 nowe powiadomienia");
+        assert_eq!(new_notifications(5), "5 nowych powiadomień");
+        assert_eq!(new_notifications(12), "12 nowych powiadomień");
+        assert_eq!(new_notifications(22), "22 nowe powiadomienia");
+        assert_eq!(new_notifications(111), "111 nowych powiadomień");
+    }
+
+    fn email_config(credentials: &str) -> EmailConfig {
+        toml::from_str(&format!(
+            r#"
+            smtp_host = "localhost"
+            from = "Szkolny <szkolny@example.org>"
+            {}
+            "#,
+            credentials
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "username and password have to be set together")]
+    async fn rejects_username_without_password() {
+        EmailSink::new("email", email_config(r#"username = "user""#));
+    }
+
+    #[tokio::test]
+    async fn accepts_credentials() {
+        EmailSink::new("email", email_config(""));
+        EmailSink::new(
+            "email",
+            email_config("username = \"user\"\npassword = \"pass\""),
+        );
+    }
+}
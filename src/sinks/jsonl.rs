@@ -0,0 +1,124 @@
+use std::{
+    error::Error,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use super::{document::NotificationDocument, DeliveryError, Sink, SinkContext};
+use crate::notification_types::ProcessedNotification;
+
+#[derive(Deserialize)]
+pub struct JsonlConfig {
+    // Writes to stdout when not set or set to "-"
+    path: Option<PathBuf>,
+    max_size_bytes: Option<u64>,
+    #[serde(default = "default_max_files")]
+    max_files: u32,
+}
+
+fn default_max_files() -> u32 {
+    5
+}
+
+impl JsonlConfig {
+    fn file_path(&self) -> Option<&Path> {
+        self.path.as_deref().filter(|path| *path != Path::new("-"))
+    }
+
+    pub fn writes_to_stdout(&self) -> bool {
+        self.file_path().is_none()
+    }
+}
+
+#[derive(Serialize)]
+pub struct JsonlLine {
+    received_at: String,
+    #[serde(flatten)]
+    document: NotificationDocument,
+}
+
+pub struct JsonlSink {
+    config: JsonlConfig,
+}
+
+impl JsonlSink {
+    pub fn new(config: JsonlConfig) -> Self {
+        JsonlSink { config }
+    }
+
+    // path -> path.1 -> path.2 -> ... -> path.<max_files>, the oldest one is dropped
+    fn rotate(&self, path: &Path) -> io::Result<()> {
+        let rotated = |i: u32| PathBuf::from(format!("{}.{}", path.display(), i));
+
+        if self.config.max_files == 0 {
+            return fs::remove_file(path);
+        }
+
+        let oldest = rotated(self.config.max_files);
+        if oldest.exists() {
+            fs::remove_file(oldest)?;
+        }
+        for i in (1..self.config.max_files).rev() {
+            if rotated(i).exists() {
+                fs::rename(rotated(i), rotated(i + 1))?;
+            }
+        }
+
+        fs::rename(path, rotated(1))
+    }
+}
+
+#[async_trait(?Send)]
+impl Sink for JsonlSink {
+    type Message = JsonlLine;
+
+    fn render(
+        &self,
+        notification: &ProcessedNotification,
+        _context: &SinkContext<'_>,
+    ) -> Result<Option<JsonlLine>, Box<dyn Error>> {
+        Ok(Some(JsonlLine {
+            received_at: OffsetDateTime::now_utc().format(&Rfc3339)?,
+            document: NotificationDocument::new(notification),
+        }))
+    }
+
+    async fn deliver(
+        &self,
+        message: &JsonlLine,
+        _context: &SinkContext<'_>,
+    ) -> Result<(), DeliveryError> {
+        let line =
+            serde_json::to_string(message).map_err(|e| DeliveryError::Permanent(e.into()))?;
+
+        let path = match self.config.file_path() {
+            Some(path) => path,
+            None => {
+                println!("{}", line);
+                return Ok(());
+            }
+        };
+
+        let write = || -> io::Result<()> {
+            if let (Some(max_size), Ok(metadata)) = (self.config.max_size_bytes, fs::metadata(path))
+            {
+                if metadata.len() > 0 && metadata.len() + line.len() as u64 + 1 > max_size {
+                    self.rotate(path)?;
+                }
+            }
+
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(file, "{}", line)
+        };
+
+        write().map_err(|e| DeliveryError::Retryable {
+            error: e.into(),
+            retry_after: None,
+        })
+    }
+}
@@ -0,0 +1,235 @@
+use std::{error::Error, time::Duration};
+
+use async_trait::async_trait;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::{text, DeliveryError, Sink, SinkContext};
+use crate::notification_types::ProcessedNotification;
+
+// Everything except the characters that are unreserved in URLs
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+#[derive(Deserialize)]
+pub struct MatrixConfig {
+    homeserver_url: Url,
+    access_token: String,
+    room_id: String,
+}
+
+#[derive(Serialize)]
+pub struct MatrixMessage {
+    #[serde(skip)]
+    txn_id: String,
+    msgtype: String,
+    body: String,
+    format: String,
+    formatted_body: String,
+}
+
+#[derive(Deserialize, Default)]
+struct MatrixErrorResponse {
+    retry_after_ms: Option<u64>,
+}
+
+pub struct MatrixSink {
+    config: MatrixConfig,
+}
+
+impl MatrixSink {
+    pub fn new(config: MatrixConfig) -> Self {
+        MatrixSink { config }
+    }
+
+    // Room IDs (`!room:server`) and transaction IDs contain characters that have to be encoded
+    fn send_url(&self, txn_id: &str) -> Result<Url, DeliveryError> {
+        if self.config.homeserver_url.cannot_be_a_base() {
+            return Err(DeliveryError::Permanent("invalid homeserver URL".into()));
+        }
+
+        let mut url = self.config.homeserver_url.clone();
+        url.set_path(&format!(
+            "{}/_matrix/client/v3/rooms/{}/send/m.room.message/{}",
+            url.path().trim_end_matches('/'),
+            utf8_percent_encode(&self.config.room_id, PATH_SEGMENT),
+            utf8_percent_encode(txn_id, PATH_SEGMENT)
+        ));
+
+        Ok(url)
+    }
+}
+
+#[async_trait(?Send)]
+impl Sink for MatrixSink {
+    type Message = MatrixMessage;
+
+    fn render(
+        &self,
+        notification: &ProcessedNotification,
+        _context: &SinkContext<'_>,
+    ) -> Result<Option<MatrixMessage>, Box<dyn Error>> {
+        Ok(Some(MatrixMessage {
+            // The homeserver drops requests with a transaction ID it has already seen, so
+            // retries and FCM redeliveries of the same notification don't post it twice
+            txn_id: format!("szkolny-{}-{}", notification.id, self.config.room_id),
+            msgtype: "m.text".to_owned(),
+            body: text::plain_text(notification),
+            format: "org.matrix.custom.html".to_owned(),
+            formatted_body: text::html(notification),
+        }))
+    }
+
+    async fn deliver(
+        &self,
+        message: &MatrixMessage,
+        context: &SinkContext<'_>,
+    ) -> Result<(), DeliveryError> {
+        let response = context
+            .client
+            .put(self.send_url(&message.txn_id)?)
+            .bearer_auth(&self.config.access_token)
+            .json(message)
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::TOO_MANY_REQUESTS {
+            let error: MatrixErrorResponse = response.json().await.unwrap_or_default();
+            return Err(DeliveryError::Retryable {
+                error: "rate limited by the homeserver".into(),
+                retry_after: error.retry_after_ms.map(Duration::from_millis),
+            });
+        }
+
+        response.error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+        sync::mpsc,
+    };
+
+    use crate::{
+        sinks::{Output, RetryConfig, SinkOutput},
+        test_util,
+    };
+
+    fn sink(homeserver_url: &str) -> MatrixSink {
+        MatrixSink::new(
+            toml::from_str(&format!(
+                r#"
+                homeserver_url = "{}"
+                access_token = "token"
+                room_id = "!room:example.org"
+                "#,
+                homeserver_url
+            ))
+            .unwrap(),
+        )
+    }
+
+    // Answers each request with the next status and sends back the request line
+    async fn homeserver(statuses: Vec<u16>) -> (String, mpsc::UnboundedReceiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            for status in statuses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut reader = BufReader::new(&mut stream);
+
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).await.unwrap();
+                let mut content_length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).await.unwrap();
+                    if header == "\r\n" {
+                        break;
+                    }
+                    if let Some(length) =
+                        header.to_ascii_lowercase().strip_prefix("content-length:")
+                    {
+                        content_length = length.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).await.unwrap();
+
+                sender.send(request_line.trim_end().to_owned()).unwrap();
+                stream
+                    .write_all(
+                        format!(
+                            "HTTP/1.1 {} Status\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{{}}",
+                            status
+                        )
+                        .as_bytes(),
+                    )
+                    .await
+                    .unwrap();
+            }
+        });
+
+        (url, receiver)
+    }
+
+    #[test]
+    fn encodes_room_id() {
+        let url = sink("https://matrix.example.org/base/")
+            .send_url("szkolny-1-!room:example.org")
+            .unwrap();
+
+        assert_eq!(
+            url.as_str(),
+            "https://matrix.example.org/base/_matrix/client/v3/rooms/%21room%3Aexample.org/send/m.room.message/szkolny-1-%21room%3Aexample.org"
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_with_the_same_transaction_id() {
+        let (url, mut requests) = homeserver(vec![500, 200]).await;
+        let output = SinkOutput {
+            name: "matrix".to_owned(),
+            retry: RetryConfig {
+                retries: 1,
+                delay_secs: 0,
+            },
+            admin: false,
+            sink: sink(&url),
+        };
+        let librus_config = test_util::librus_config();
+        let context = SinkContext {
+            client: &reqwest::Client::new(),
+            database: &rusqlite::Connection::open_in_memory().unwrap(),
+            librus_config: &librus_config,
+        };
+
+        output
+            .send(&test_util::shared_event(json!({})), &context)
+            .await
+            .unwrap();
+
+        let first = requests.recv().await.unwrap();
+        let second = requests.recv().await.unwrap();
+        assert_eq!(
+            first,
+            "PUT /_matrix/client/v3/rooms/%21room%3Aexample.org/send/m.room.message/szkolny-1-%21room%3Aexample.org HTTP/1.1"
+        );
+        assert_eq!(first, second);
+    }
+}
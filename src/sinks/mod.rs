@@ -0,0 +1,374 @@
+mod command;
+mod discord;
+mod document;
+mod email;
+mod jsonl;
+mod matrix;
+mod mqtt;
+mod push;
+mod slack;
+mod telegram;
+mod text;
+mod webhook;
+
+use std::{error::Error, fmt, time::Duration};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{notification_types::ProcessedNotification, LibrusConfig};
+
+use command::CommandConfig;
+pub use discord::DiscordConfig;
+use email::EmailConfig;
+use jsonl::JsonlConfig;
+use matrix::MatrixConfig;
+use mqtt::MqttConfig;
+use push::{GotifyConfig, NtfyConfig};
+use slack::SlackConfig;
+use telegram::TelegramConfig;
+use webhook::WebhookConfig;
+
+#[derive(Deserialize)]
+pub struct OutputConfig {
+    name: Option<String>,
+    #[serde(default)]
+    retry: RetryConfig,
+    // Admin outputs only get administrative messages, see `AdminMessagesConfig`
+    #[serde(default)]
+    admin: bool,
+    #[serde(flatten)]
+    kind: OutputKind,
+}
+
+impl From<DiscordConfig> for OutputConfig {
+    fn from(discord_config: DiscordConfig) -> Self {
+        OutputConfig {
+            name: Some("discord".to_owned()),
+            retry: RetryConfig::default(),
+            admin: false,
+            kind: OutputKind::Discord(discord_config),
+        }
+    }
+}
+
+impl OutputConfig {
+    pub fn writes_to_stdout(&self) -> bool {
+        matches!(&self.kind, OutputKind::Jsonl(jsonl_config) if jsonl_config.writes_to_stdout())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OutputKind {
+    Discord(DiscordConfig),
+    Matrix(MatrixConfig),
+    Telegram(TelegramConfig),
+    Ntfy(NtfyConfig),
+    Gotify(GotifyConfig),
+    Email(EmailConfig),
+    Webhook(WebhookConfig),
+    Slack(SlackConfig),
+    Mqtt(MqttConfig),
+    Command(CommandConfig),
+    Jsonl(JsonlConfig),
+}
+
+#[derive(Deserialize, Clone)]
+pub struct RetryConfig {
+    #[serde(default = "default_retries")]
+    retries: u32,
+    #[serde(default = "default_retry_delay")]
+    delay_secs: u64,
+}
+
+fn default_retries() -> u32 {
+    5
+}
+
+fn default_retry_delay() -> u64 {
+    30
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            retries: default_retries(),
+            delay_secs: default_retry_delay(),
+        }
+    }
+}
+
+pub struct SinkContext<'a> {
+    pub client: &'a reqwest::Client,
+    pub database: &'a rusqlite::Connection,
+    pub librus_config: &'a LibrusConfig,
+}
+
+#[derive(Debug)]
+pub enum DeliveryError {
+    Retryable {
+        error: Box<dyn Error>,
+        retry_after: Option<Duration>,
+    },
+    Permanent(Box<dyn Error>),
+}
+
+impl fmt::Display for DeliveryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeliveryError::Retryable { error, .. } => write!(f, "{}", error),
+            DeliveryError::Permanent(error) => write!(f, "{} (not retrying)", error),
+        }
+    }
+}
+
+impl Error for DeliveryError {}
+
+impl From<reqwest::Error> for DeliveryError {
+    fn from(error: reqwest::Error) -> Self {
+        match error.status() {
+            Some(status)
+                if status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS =>
+            {
+                DeliveryError::Permanent(error.into())
+            }
+            _ => DeliveryError::Retryable {
+                error: error.into(),
+                retry_after: None,
+            },
+        }
+    }
+}
+
+impl From<rusqlite::Error> for DeliveryError {
+    fn from(error: rusqlite::Error) -> Self {
+        DeliveryError::Permanent(error.into())
+    }
+}
+
+// A sink turns a processed notification into its own message format and then delivers it.
+// Rendering returns `None` when the sink has nothing to send for a notification.
+#[async_trait(?Send)]
+pub trait Sink {
+    type Message: Serialize;
+
+    fn render(
+        &self,
+        notification: &ProcessedNotification,
+        context: &SinkContext<'_>,
+    ) -> Result<Option<Self::Message>, Box<dyn Error>>;
+
+    async fn deliver(
+        &self,
+        message: &Self::Message,
+        context: &SinkContext<'_>,
+    ) -> Result<(), DeliveryError>;
+
+    // What dry runs print, sinks that send more than the serialized message show it here
+    fn preview(
+        &self,
+        message: &Self::Message,
+        _context: &SinkContext<'_>,
+    ) -> Result<serde_json::Value, Box<dyn Error>> {
+        Ok(serde_json::to_value(message)?)
+    }
+
+    // Called periodically, for sinks that do work outside of incoming notifications
+    async fn tick(&self, _context: &SinkContext<'_>) -> Result<(), DeliveryError> {
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+pub trait Output {
+    fn name(&self) -> &str;
+
+    fn admin(&self) -> bool;
+
+    async fn send(
+        &self,
+        notification: &ProcessedNotification,
+        context: &SinkContext<'_>,
+    ) -> Result<(), Box<dyn Error>>;
+
+    async fn tick(&self, context: &SinkContext<'_>) -> Result<(), Box<dyn Error>>;
+
+    fn render_json(
+        &self,
+        notification: &ProcessedNotification,
+        context: &SinkContext<'_>,
+    ) -> Result<Option<serde_json::Value>, Box<dyn Error>>;
+}
+
+struct SinkOutput<S: Sink> {
+    name: String,
+    retry: RetryConfig,
+    admin: bool,
+    sink: S,
+}
+
+#[async_trait(?Send)]
+impl<S: Sink> Output for SinkOutput<S> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn admin(&self) -> bool {
+        self.admin
+    }
+
+    async fn send(
+        &self,
+        notification: &ProcessedNotification,
+        context: &SinkContext<'_>,
+    ) -> Result<(), Box<dyn Error>> {
+        let message = match self.sink.render(notification, context)? {
+            Some(message) => message,
+            None => return Ok(()),
+        };
+
+        let mut retries = 0;
+        loop {
+            match self.sink.deliver(&message, context).await {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    eprintln!("  -> Failed to send message to {}! {}", self.name, e);
+
+                    let retry_after = match e {
+                        DeliveryError::Retryable { retry_after, .. }
+                            if retries < self.retry.retries =>
+                        {
+                            retry_after
+                        }
+                        e => return Err(e.into()),
+                    };
+
+                    tokio::time::sleep(
+                        retry_after.unwrap_or(Duration::from_secs(self.retry.delay_secs)),
+                    )
+                    .await;
+                    retries += 1;
+                }
+            }
+        }
+    }
+
+    async fn tick(&self, context: &SinkContext<'_>) -> Result<(), Box<dyn Error>> {
+        self.sink.tick(context).await?;
+
+        Ok(())
+    }
+
+    fn render_json(
+        &self,
+        notification: &ProcessedNotification,
+        context: &SinkContext<'_>,
+    ) -> Result<Option<serde_json::Value>, Box<dyn Error>> {
+        match self.sink.render(notification, context)? {
+            Some(message) => Ok(Some(self.sink.preview(&message, context)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+pub fn build_outputs(configs: Vec<OutputConfig>) -> Vec<Box<dyn Output>> {
+    configs
+        .into_iter()
+        .enumerate()
+        .map(|(i, config)| {
+            let name = config
+                .name
+                .unwrap_or_else(|| format!("{}-{}", config.kind.type_name(), i + 1));
+
+            let output: Box<dyn Output> = match config.kind {
+                OutputKind::Discord(discord_config) => Box::new(SinkOutput {
+                    name,
+                    retry: config.retry,
+                    admin: config.admin,
+                    sink: discord::DiscordSink::new(discord_config),
+                }),
+                OutputKind::Matrix(matrix_config) => Box::new(SinkOutput {
+                    name,
+                    retry: config.retry,
+                    admin: config.admin,
+                    sink: matrix::MatrixSink::new(matrix_config),
+                }),
+                OutputKind::Telegram(telegram_config) => Box::new(SinkOutput {
+                    name,
+                    retry: config.retry,
+                    admin: config.admin,
+                    sink: telegram::TelegramSink::new(telegram_config),
+                }),
+                OutputKind::Ntfy(ntfy_config) => Box::new(SinkOutput {
+                    name,
+                    retry: config.retry,
+                    admin: config.admin,
+                    sink: push::NtfySink::new(ntfy_config),
+                }),
+                OutputKind::Gotify(gotify_config) => Box::new(SinkOutput {
+                    name,
+                    retry: config.retry,
+                    admin: config.admin,
+                    sink: push::GotifySink::new(gotify_config),
+                }),
+                OutputKind::Email(email_config) => Box::new(SinkOutput {
+                    sink: email::EmailSink::new(&name, email_config),
+                    name,
+                    retry: config.retry,
+                    admin: config.admin,
+                }),
+                OutputKind::Webhook(webhook_config) => Box::new(SinkOutput {
+                    name,
+                    retry: config.retry,
+                    admin: config.admin,
+                    sink: webhook::WebhookSink::new(webhook_config),
+                }),
+                OutputKind::Slack(slack_config) => Box::new(SinkOutput {
+                    name,
+                    retry: config.retry,
+                    admin: config.admin,
+                    sink: slack::SlackSink::new(slack_config),
+                }),
+                OutputKind::Mqtt(mqtt_config) => Box::new(SinkOutput {
+                    sink: mqtt::MqttSink::new(&name, mqtt_config),
+                    name,
+                    retry: config.retry,
+                    admin: config.admin,
+                }),
+                OutputKind::Command(command_config) => Box::new(SinkOutput {
+                    name,
+                    retry: config.retry,
+                    admin: config.admin,
+                    sink: command::CommandSink::new(command_config),
+                }),
+                OutputKind::Jsonl(jsonl_config) => Box::new(SinkOutput {
+                    name,
+                    retry: config.retry,
+                    admin: config.admin,
+                    sink: jsonl::JsonlSink::new(jsonl_config),
+                }),
+            };
+
+            output
+        })
+        .collect()
+}
+
+impl OutputKind {
+    fn type_name(&self) -> &'static str {
+        match self {
+            OutputKind::Discord(_) => "discord",
+            OutputKind::Matrix(_) => "matrix",
+            OutputKind::Telegram(_) => "telegram",
+            OutputKind::Ntfy(_) => "ntfy",
+            OutputKind::Gotify(_) => "gotify",
+            OutputKind::Email(_) => "email",
+            OutputKind::Webhook(_) => "webhook",
+            OutputKind::Slack(_) => "slack",
+            OutputKind::Mqtt(_) => "mqtt",
+            OutputKind::Command(_) => "command",
+            OutputKind::Jsonl(_) => "jsonl",
+        }
+    }
+}
@@ -0,0 +1,342 @@
+use std::{
+    cell::RefCell,
+    error::Error,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS};
+use serde::{Deserialize, Deserializer, Serialize};
+use time::OffsetDateTime;
+
+use super::{document::NotificationDocument, DeliveryError, Sink, SinkContext};
+use crate::{
+    db,
+    icalendar::CalendarEvent,
+    notification_types::{ProcessedNotification, TEST_EVENT_TYPES},
+};
+
+#[derive(Deserialize)]
+pub struct MqttConfig {
+    host: String,
+    #[serde(default = "default_port")]
+    port: u16,
+    #[serde(default = "default_client_id")]
+    client_id: String,
+    username: Option<String>,
+    password: Option<String>,
+    #[serde(default = "default_topic")]
+    topic: String,
+    #[serde(default = "default_qos", deserialize_with = "deserialize_qos")]
+    qos: QoS,
+    #[serde(default)]
+    retain: bool,
+    next_test_topic: Option<String>,
+}
+
+fn default_port() -> u16 {
+    1883
+}
+
+fn default_client_id() -> String {
+    env!("CARGO_PKG_NAME").to_owned()
+}
+
+fn default_topic() -> String {
+    "szkolny/{team}/{type}".to_owned()
+}
+
+fn default_qos() -> QoS {
+    QoS::AtMostOnce
+}
+
+fn deserialize_qos<'de, D: Deserializer<'de>>(deserializer: D) -> Result<QoS, D::Error> {
+    let qos = u8::deserialize(deserializer)?;
+    rumqttc::qos(qos).map_err(|_| serde::de::Error::custom(format!("invalid QoS level: {}", qos)))
+}
+
+#[derive(Serialize)]
+pub struct MqttMessage {
+    topic: String,
+    payload: String,
+    #[serde(skip)]
+    next_test_update: Option<NextTestUpdate>,
+}
+
+enum NextTestUpdate {
+    Add(UpcomingTest),
+    Remove(String),
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct UpcomingTest {
+    event_id: String,
+    time: i64,
+    payload: String,
+}
+
+pub struct MqttSink {
+    name: String,
+    config: MqttConfig,
+    client: AsyncClient,
+    // Publishing only queues the message, so without a connection it would wait there unnoticed
+    connected: Arc<AtomicBool>,
+    // Event ID of the test last published to `next_test_topic`, `Some(None)` means it was cleared
+    published_next_test: RefCell<Option<Option<String>>>,
+}
+
+impl MqttSink {
+    pub fn new(name: &str, config: MqttConfig) -> Self {
+        let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            options.set_credentials(username, password);
+        }
+
+        let (client, mut event_loop) = AsyncClient::new(options, 16);
+
+        let name = name.to_owned();
+        let event_loop_name = name.clone();
+        let connected = Arc::new(AtomicBool::new(false));
+        let event_loop_connected = connected.clone();
+        tokio::spawn(async move {
+            loop {
+                match event_loop.poll().await {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        event_loop_connected.store(true, Ordering::Relaxed);
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        event_loop_connected.store(false, Ordering::Relaxed);
+                        eprintln!("  -> {}: MQTT connection error: {}", event_loop_name, e);
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                    }
+                }
+            }
+        });
+
+        MqttSink {
+            name,
+            config,
+            client,
+            connected,
+            published_next_test: RefCell::new(None),
+        }
+    }
+
+    // Fails instead of waiting when the request queue is full, so a broker that is down
+    // doesn't hold up the other outputs
+    fn publish(&self, topic: &str, retain: bool, payload: String) -> Result<(), DeliveryError> {
+        if !self.connected.load(Ordering::Relaxed) {
+            return Err(DeliveryError::Retryable {
+                error: "not connected to the MQTT broker".into(),
+                retry_after: None,
+            });
+        }
+
+        self.client
+            .try_publish(topic, self.config.qos, retain, payload)
+            .map_err(mqtt_error)
+    }
+
+    fn topic(&self, notification: &ProcessedNotification) -> String {
+        let embed = &notification.embed;
+
+        self.config
+            .topic
+            .replace(
+                "{team}",
+                &topic_level(embed.team_code.as_deref().unwrap_or("none")),
+            )
+            .replace("{type}", &topic_level(&embed.notification_type))
+            .replace(
+                "{subject}",
+                &embed
+                    .subject_id
+                    .map(|id| id.to_string())
+                    .unwrap_or("none".to_owned()),
+            )
+            .replace(
+                "{event_type}",
+                &embed
+                    .event_type
+                    .map(|id| id.to_string())
+                    .unwrap_or("none".to_owned()),
+            )
+    }
+
+    fn upcoming_tests_key(&self) -> String {
+        format!("mqtt_upcoming_tests:{}", self.name)
+    }
+
+    async fn publish_next_test(&self, context: &SinkContext<'_>) -> Result<(), DeliveryError> {
+        let next_test_topic = match &self.config.next_test_topic {
+            Some(next_test_topic) => next_test_topic,
+            None => return Ok(()),
+        };
+
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let mut tests: Vec<UpcomingTest> =
+            db::get_data(context.database, &self.upcoming_tests_key())?.unwrap_or_default();
+        tests.retain(|test| test.time >= now);
+        db::set_data(context.database, &self.upcoming_tests_key(), &tests)?;
+
+        let next_test = tests.iter().min_by_key(|test| test.time);
+        let next_test_id = next_test.map(|test| test.event_id.clone());
+
+        if self.published_next_test.borrow().as_ref() == Some(&next_test_id) {
+            return Ok(());
+        }
+
+        // An empty retained message clears the topic
+        let payload = next_test
+            .map(|test| test.payload.clone())
+            .unwrap_or_default();
+        self.publish(next_test_topic, true, payload)?;
+
+        *self.published_next_test.borrow_mut() = Some(next_test_id);
+
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl Sink for MqttSink {
+    type Message = MqttMessage;
+
+    fn render(
+        &self,
+        notification: &ProcessedNotification,
+        _context: &SinkContext<'_>,
+    ) -> Result<Option<MqttMessage>, Box<dyn Error>> {
+        let payload = serde_json::to_string(&NotificationDocument::new(notification))?;
+        let embed = &notification.embed;
+
+        let next_test_update = match &embed.calendar_event {
+            Some(CalendarEvent::Publish {
+                event_id, start, ..
+            }) if embed
+                .event_type
+                .is_some_and(|event_type| TEST_EVENT_TYPES.contains(&event_type)) =>
+            {
+                Some(NextTestUpdate::Add(UpcomingTest {
+                    event_id: event_id.clone(),
+                    time: start.unix_timestamp(),
+                    payload: payload.clone(),
+                }))
+            }
+            Some(calendar_event) => {
+                Some(NextTestUpdate::Remove(calendar_event.event_id().to_owned()))
+            }
+            None => None,
+        };
+
+        Ok(Some(MqttMessage {
+            topic: self.topic(notification),
+            payload,
+            next_test_update,
+        }))
+    }
+
+    async fn deliver(
+        &self,
+        message: &MqttMessage,
+        context: &SinkContext<'_>,
+    ) -> Result<(), DeliveryError> {
+        self.publish(&message.topic, self.config.retain, message.payload.clone())?;
+
+        if self.config.next_test_topic.is_none() {
+            return Ok(());
+        }
+
+        if let Some(update) = &message.next_test_update {
+            let mut tests: Vec<UpcomingTest> =
+                db::get_data(context.database, &self.upcoming_tests_key())?.unwrap_or_default();
+
+            // A shared event can also be an edit of an existing one, so it always replaces
+            let event_id = match update {
+                NextTestUpdate::Add(test) => &test.event_id,
+                NextTestUpdate::Remove(event_id) => event_id,
+            };
+            tests.retain(|test| &test.event_id != event_id);
+            if let NextTestUpdate::Add(test) = update {
+                tests.push(test.clone());
+            }
+
+            db::set_data(context.database, &self.upcoming_tests_key(), &tests)?;
+        }
+
+        self.publish_next_test(context).await
+    }
+
+    async fn tick(&self, context: &SinkContext<'_>) -> Result<(), DeliveryError> {
+        self.publish_next_test(context).await
+    }
+}
+
+fn mqtt_error(error: rumqttc::ClientError) -> DeliveryError {
+    DeliveryError::Retryable {
+        error: error.into(),
+        retry_after: None,
+    }
+}
+
+// Wildcards and separators would change the meaning of the topic
+fn topic_level(value: &str) -> String {
+    value.replace(['/', '+', '#'], "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    use crate::test_util;
+
+    fn mqtt_config(extra: &str) -> Result<MqttConfig, toml::de::Error> {
+        toml::from_str(&format!(
+            r#"
+            host = "127.0.0.1"
+            port = 1
+            {}
+            "#,
+            extra
+        ))
+    }
+
+    #[test]
+    fn parses_qos() {
+        assert_eq!(mqtt_config("").unwrap().qos, QoS::AtMostOnce);
+        assert_eq!(mqtt_config("qos = 2").unwrap().qos, QoS::ExactlyOnce);
+    }
+
+    #[test]
+    fn rejects_invalid_qos() {
+        let error = mqtt_config("qos = 3").err().unwrap();
+        assert!(error.to_string().contains("invalid QoS level: 3"));
+    }
+
+    #[tokio::test]
+    async fn retries_without_connection() {
+        let sink = MqttSink::new("mqtt", mqtt_config("").unwrap());
+        let librus_config = test_util::librus_config();
+        let context = SinkContext {
+            client: &reqwest::Client::new(),
+            database: &rusqlite::Connection::open_in_memory().unwrap(),
+            librus_config: &librus_config,
+        };
+
+        let message = sink
+            .render(&test_util::shared_event(json!({})), &context)
+            .unwrap()
+            .unwrap();
+        let result = sink.deliver(&message, &context).await;
+
+        assert!(matches!(result, Err(DeliveryError::Retryable { .. })));
+    }
+}
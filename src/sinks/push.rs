@@ -0,0 +1,271 @@
+use std::error::Error;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::{DeliveryError, Sink, SinkContext};
+use crate::{
+    notification_filter::NotificationFilter,
+    notification_types::{NotificationEmbed, ProcessedNotification, TEST_EVENT_TYPES},
+    szkolny_push::PushKind,
+};
+
+#[derive(Deserialize, Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum PushPriority {
+    Min,
+    Low,
+    Default,
+    High,
+    Max,
+}
+
+impl PushPriority {
+    fn ntfy(self) -> u8 {
+        match self {
+            PushPriority::Min => 1,
+            PushPriority::Low => 2,
+            PushPriority::Default => 3,
+            PushPriority::High => 4,
+            PushPriority::Max => 5,
+        }
+    }
+
+    fn gotify(self) -> u8 {
+        match self {
+            PushPriority::Min => 0,
+            PushPriority::Low => 2,
+            PushPriority::Default => 5,
+            PushPriority::High => 8,
+            PushPriority::Max => 10,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PriorityRule {
+    #[serde(flatten)]
+    filter: NotificationFilter,
+    priority: Option<PushPriority>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct NtfyConfig {
+    server_url: Url,
+    topic: String,
+    token: Option<String>,
+    #[serde(default)]
+    priorities: Vec<PriorityRule>,
+}
+
+#[derive(Deserialize)]
+pub struct GotifyConfig {
+    server_url: Url,
+    app_token: String,
+    #[serde(default)]
+    priorities: Vec<PriorityRule>,
+}
+
+#[derive(Serialize)]
+pub struct PushMessage {
+    title: String,
+    message: String,
+    priority: PushPriority,
+    tags: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct NtfyBody<'a> {
+    topic: &'a str,
+    title: &'a str,
+    message: &'a str,
+    priority: u8,
+    tags: &'a [String],
+}
+
+#[derive(Serialize)]
+struct GotifyBody<'a> {
+    title: &'a str,
+    message: &'a str,
+    priority: u8,
+}
+
+fn render_push(notification: &ProcessedNotification, rules: &[PriorityRule]) -> PushMessage {
+    let embed = &notification.embed;
+
+    let mut priority = None;
+    let mut tags = Vec::new();
+    for rule in rules.iter().filter(|rule| rule.filter.matches(embed)) {
+        priority = priority.or(rule.priority);
+        for tag in &rule.tags {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+    }
+
+    if tags.is_empty() {
+        tags.push(embed.notification_type.clone());
+    }
+
+    PushMessage {
+        title: notification.title.clone(),
+        message: embed
+            .description
+            .clone()
+            .unwrap_or_else(|| notification.message.clone()),
+        priority: priority.unwrap_or_else(|| default_priority(embed)),
+        tags,
+    }
+}
+
+fn default_priority(embed: &NotificationEmbed) -> PushPriority {
+    match embed.kind {
+        PushKind::SharedEvent => match embed.event_type {
+            Some(event_type) if TEST_EVENT_TYPES.contains(&event_type) => PushPriority::High,
+            _ => PushPriority::Default,
+        },
+        PushKind::UnsharedEvent | PushKind::SharedNote | PushKind::UnsharedNote => {
+            PushPriority::Default
+        }
+        _ => PushPriority::Low,
+    }
+}
+
+pub struct NtfySink {
+    config: NtfyConfig,
+}
+
+impl NtfySink {
+    pub fn new(config: NtfyConfig) -> Self {
+        NtfySink { config }
+    }
+}
+
+#[async_trait(?Send)]
+impl Sink for NtfySink {
+    type Message = PushMessage;
+
+    fn render(
+        &self,
+        notification: &ProcessedNotification,
+        _context: &SinkContext<'_>,
+    ) -> Result<Option<PushMessage>, Box<dyn Error>> {
+        Ok(Some(render_push(notification, &self.config.priorities)))
+    }
+
+    // Publishing as JSON keeps non-ASCII titles intact, which headers would not
+    async fn deliver(
+        &self,
+        message: &PushMessage,
+        context: &SinkContext<'_>,
+    ) -> Result<(), DeliveryError> {
+        let mut request = context
+            .client
+            .post(self.config.server_url.clone())
+            .json(&NtfyBody {
+                topic: &self.config.topic,
+                title: &message.title,
+                message: &message.message,
+                priority: message.priority.ntfy(),
+                tags: &message.tags,
+            });
+
+        if let Some(token) = &self.config.token {
+            request = request.bearer_auth(token);
+        }
+
+        request.send().await?.error_for_status()?;
+
+        Ok(())
+    }
+}
+
+pub struct GotifySink {
+    config: GotifyConfig,
+}
+
+impl GotifySink {
+    pub fn new(config: GotifyConfig) -> Self {
+        GotifySink { config }
+    }
+}
+
+#[async_trait(?Send)]
+impl Sink for GotifySink {
+    type Message = PushMessage;
+
+    fn render(
+        &self,
+        notification: &ProcessedNotification,
+        _context: &SinkContext<'_>,
+    ) -> Result<Option<PushMessage>, Box<dyn Error>> {
+        Ok(Some(render_push(notification, &self.config.priorities)))
+    }
+
+    async fn deliver(
+        &self,
+        message: &PushMessage,
+        context: &SinkContext<'_>,
+    ) -> Result<(), DeliveryError> {
+        let mut url = self.config.server_url.clone();
+        url.path_segments_mut()
+            .map_err(|_| DeliveryError::Permanent("invalid Gotify server URL".into()))?
+            .pop_if_empty()
+            .push("message");
+
+        context
+            .client
+            .post(url)
+            .header("X-Gotify-Key", &self.config.app_token)
+            .json(&GotifyBody {
+                title: &message.title,
+                message: &message.message,
+                priority: message.priority.gotify(),
+            })
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    use crate::test_util;
+
+    fn priority(notification: &ProcessedNotification) -> u8 {
+        render_push(notification, &[]).priority.ntfy()
+    }
+
+    #[test]
+    fn prioritizes_tests() {
+        assert_eq!(
+            priority(&test_util::shared_event(json!({ "type": 1 }))),
+            PushPriority::High.ntfy()
+        );
+        assert_eq!(
+            priority(&test_util::shared_event(json!({ "type": 0 }))),
+            PushPriority::Default.ntfy()
+        );
+    }
+
+    #[test]
+    fn lowers_other_pushes() {
+        let notification = test_util::process(json!({
+            "type": "sharedEvent2",
+            "title": "Szkolny.eu",
+            "message": "Nieznane powiadomienie"
+        }));
+
+        assert_eq!(priority(&notification), PushPriority::Low.ntfy());
+    }
+}
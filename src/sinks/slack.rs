@@ -0,0 +1,244 @@
+use std::error::Error;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::{text, DeliveryError, Sink, SinkContext};
+use crate::notification_types::{NotificationTimestamp, ProcessedNotification, TimestampStyle};
+
+// https://api.slack.com/reference/block-kit/blocks
+const MAX_HEADER_LENGTH: usize = 150;
+const MAX_SECTION_TEXT_LENGTH: usize = 3000;
+const MAX_FIELD_TEXT_LENGTH: usize = 2000;
+const MAX_FIELDS: usize = 10;
+const MAX_FALLBACK_LENGTH: usize = 4000;
+
+#[derive(Deserialize)]
+pub struct SlackConfig {
+    webhook_url: Url,
+}
+
+#[derive(Serialize)]
+pub struct SlackMessage {
+    text: String,
+    blocks: Vec<Block>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Block {
+    Header {
+        text: TextObject,
+    },
+    Section {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        text: Option<TextObject>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        fields: Vec<TextObject>,
+    },
+    Context {
+        elements: Vec<TextObject>,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum TextObject {
+    PlainText { text: String },
+    Mrkdwn { text: String },
+}
+
+pub struct SlackSink {
+    config: SlackConfig,
+}
+
+impl SlackSink {
+    pub fn new(config: SlackConfig) -> Self {
+        SlackSink { config }
+    }
+}
+
+#[async_trait(?Send)]
+impl Sink for SlackSink {
+    type Message = SlackMessage;
+
+    fn render(
+        &self,
+        notification: &ProcessedNotification,
+        _context: &SinkContext<'_>,
+    ) -> Result<Option<SlackMessage>, Box<dyn Error>> {
+        let embed = &notification.embed;
+        let mut blocks = Vec::new();
+
+        // Slack rejects blocks with empty text, the message and description of shared events
+        // come from other users and can be empty
+        if !notification.message.trim().is_empty() {
+            blocks.push(Block::Header {
+                text: TextObject::PlainText {
+                    text: truncate(&notification.message, MAX_HEADER_LENGTH),
+                },
+            });
+        }
+
+        let section_text = match (&embed.description, &embed.code_block) {
+            (Some(description), _) => Some(escape_mrkdwn(description)),
+            (None, Some(code)) => Some(format!("```{}```", escape_mrkdwn(code))),
+            (None, None) => None,
+        };
+        let fields: Vec<TextObject> = embed
+            .fields
+            .iter()
+            .take(MAX_FIELDS)
+            .map(|field| TextObject::Mrkdwn {
+                text: truncate(
+                    &format!(
+                        "*{}*\n{}",
+                        escape_mrkdwn(&field.name),
+                        match &field.timestamp {
+                            Some(timestamp) => slack_date(timestamp, &field.value),
+                            None => escape_mrkdwn(&field.value),
+                        }
+                    ),
+                    MAX_FIELD_TEXT_LENGTH,
+                ),
+            })
+            .collect();
+
+        if let Some(section_text) = section_text.filter(|text| !text.trim().is_empty()) {
+            blocks.push(Block::Section {
+                text: Some(TextObject::Mrkdwn {
+                    text: truncate(&section_text, MAX_SECTION_TEXT_LENGTH),
+                }),
+                fields: vec![],
+            });
+        }
+        if !fields.is_empty() {
+            blocks.push(Block::Section { text: None, fields });
+        }
+
+        let mut context_elements = Vec::new();
+        if let Some(author) = embed
+            .author
+            .as_ref()
+            .filter(|author| !author.trim().is_empty())
+        {
+            context_elements.push(TextObject::PlainText {
+                text: truncate(author, MAX_FIELD_TEXT_LENGTH),
+            });
+        }
+        context_elements.push(TextObject::PlainText {
+            text: truncate(&text::footer(notification), MAX_FIELD_TEXT_LENGTH),
+        });
+        blocks.push(Block::Context {
+            elements: context_elements,
+        });
+
+        Ok(Some(SlackMessage {
+            text: truncate(&text::plain_text(notification), MAX_FALLBACK_LENGTH),
+            blocks,
+        }))
+    }
+
+    async fn deliver(
+        &self,
+        message: &SlackMessage,
+        context: &SinkContext<'_>,
+    ) -> Result<(), DeliveryError> {
+        context
+            .client
+            .post(self.config.webhook_url.clone())
+            .json(message)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+fn slack_date(timestamp: &NotificationTimestamp, fallback: &str) -> String {
+    let format = match timestamp.style {
+        TimestampStyle::Date => "{date_long_pretty}",
+        TimestampStyle::DateTime => "{date_long_pretty} {time}",
+        TimestampStyle::Time => "{time}",
+    };
+
+    format!(
+        "<!date^{}^{}|{}>",
+        timestamp.time.unix_timestamp(),
+        format,
+        escape_mrkdwn(fallback)
+    )
+}
+
+// Slack only requires these three to be escaped, everything else is taken literally or as mrkdwn
+fn escape_mrkdwn(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn truncate(text: &str, max_length: usize) -> String {
+    match text.char_indices().nth(max_length - 1) {
+        Some((end, _)) if text[end..].chars().nth(1).is_some() => format!("{}…", &text[..end]),
+        _ => text.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::{json, Value};
+
+    use crate::test_util;
+
+    fn render(notification: &ProcessedNotification) -> Value {
+        let sink = SlackSink::new(
+            toml::from_str(r#"webhook_url = "https://hooks.slack.com/services/x""#).unwrap(),
+        );
+        let librus_config = test_util::librus_config();
+        let context = SinkContext {
+            client: &reqwest::Client::new(),
+            database: &rusqlite::Connection::open_in_memory().unwrap(),
+            librus_config: &librus_config,
+        };
+
+        serde_json::to_value(sink.render(notification, &context).unwrap().unwrap()).unwrap()
+    }
+
+    fn block_types(message: &Value) -> Vec<&str> {
+        message["blocks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|block| block["type"].as_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn renders_blocks() {
+        let message = render(&test_util::shared_event(json!({})));
+
+        assert_eq!(
+            block_types(&message),
+            ["header", "section", "section", "context"]
+        );
+        assert_eq!(
+            message["blocks"][0]["text"]["text"],
+            "Jan Kowalski dodał wydarzenie"
+        );
+    }
+
+    #[test]
+    fn skips_blocks_without_text() {
+        let message = render(&test_util::shared_event(json!({ "topic": " " })));
+        assert_eq!(block_types(&message), ["header", "section", "context"]);
+
+        let mut notification = test_util::shared_event(json!({ "topic": "" }));
+        notification.message = String::new();
+        let message = render(&notification);
+        assert_eq!(block_types(&message), ["section", "context"]);
+    }
+}
@@ -0,0 +1,277 @@
+use std::{cell::RefCell, collections::HashSet, error::Error, time::Duration};
+
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::{text, DeliveryError, Sink, SinkContext};
+use crate::notification_types::ProcessedNotification;
+
+const MAX_DESCRIPTION_LENGTH: usize = 3000;
+const MARKDOWN_V2_CHARS: &[char] = &[
+    '\\', '_', '*', '[', ']', '(', ')', '~', '`', '>', '#', '+', '-', '=', '|', '{', '}', '.', '!',
+];
+
+#[derive(Deserialize)]
+pub struct TelegramConfig {
+    bot_token: String,
+    chat_ids: Vec<ChatId>,
+    #[serde(default = "default_api_url")]
+    api_url: Url,
+    #[serde(default)]
+    parse_mode: ParseMode,
+}
+
+fn default_api_url() -> Url {
+    Url::parse("https://api.telegram.org").unwrap()
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(untagged)]
+enum ChatId {
+    Id(i64),
+    Username(String),
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Default)]
+enum ParseMode {
+    #[default]
+    #[serde(rename = "HTML", alias = "html")]
+    Html,
+    #[serde(rename = "MarkdownV2", alias = "markdown_v2")]
+    MarkdownV2,
+}
+
+#[derive(Serialize)]
+pub struct TelegramMessage {
+    text: String,
+    parse_mode: ParseMode,
+    // Chats that already got the message, so a retry only goes to the ones that failed
+    #[serde(skip)]
+    delivered: RefCell<HashSet<usize>>,
+}
+
+#[derive(Serialize)]
+struct SendMessageBody<'a> {
+    chat_id: &'a ChatId,
+    text: &'a str,
+    parse_mode: ParseMode,
+    disable_web_page_preview: bool,
+}
+
+#[derive(Deserialize, Default)]
+struct TelegramErrorResponse {
+    parameters: Option<TelegramResponseParameters>,
+}
+
+#[derive(Deserialize)]
+struct TelegramResponseParameters {
+    retry_after: Option<u64>,
+}
+
+pub struct TelegramSink {
+    config: TelegramConfig,
+}
+
+impl TelegramSink {
+    pub fn new(config: TelegramConfig) -> Self {
+        TelegramSink { config }
+    }
+}
+
+#[async_trait(?Send)]
+impl Sink for TelegramSink {
+    type Message = TelegramMessage;
+
+    fn render(
+        &self,
+        notification: &ProcessedNotification,
+        _context: &SinkContext<'_>,
+    ) -> Result<Option<TelegramMessage>, Box<dyn Error>> {
+        let text = match self.config.parse_mode {
+            ParseMode::Html => render_html(notification),
+            ParseMode::MarkdownV2 => render_markdown_v2(notification),
+        };
+
+        Ok(Some(TelegramMessage {
+            text,
+            parse_mode: self.config.parse_mode,
+            delivered: RefCell::new(HashSet::new()),
+        }))
+    }
+
+    async fn deliver(
+        &self,
+        message: &TelegramMessage,
+        context: &SinkContext<'_>,
+    ) -> Result<(), DeliveryError> {
+        let mut url = self.config.api_url.clone();
+        url.path_segments_mut()
+            .map_err(|_| DeliveryError::Permanent("invalid Telegram API URL".into()))?
+            .pop_if_empty()
+            .extend(&[&format!("bot{}", self.config.bot_token), "sendMessage"]);
+
+        for (i, chat_id) in self.config.chat_ids.iter().enumerate() {
+            if message.delivered.borrow().contains(&i) {
+                continue;
+            }
+
+            let response = context
+                .client
+                .post(url.clone())
+                .json(&SendMessageBody {
+                    chat_id,
+                    text: &message.text,
+                    parse_mode: message.parse_mode,
+                    disable_web_page_preview: true,
+                })
+                .send()
+                .await?;
+
+            if response.status() == StatusCode::TOO_MANY_REQUESTS {
+                let error: TelegramErrorResponse = response.json().await.unwrap_or_default();
+                return Err(DeliveryError::Retryable {
+                    error: "rate limited by Telegram".into(),
+                    retry_after: error
+                        .parameters
+                        .and_then(|parameters| parameters.retry_after)
+                        .map(Duration::from_secs),
+                });
+            }
+
+            response.error_for_status()?;
+            message.delivered.borrow_mut().insert(i);
+        }
+
+        Ok(())
+    }
+}
+
+// Telegram's HTML mode only knows a handful of inline tags and uses plain newlines for breaks
+fn render_html(notification: &ProcessedNotification) -> String {
+    let embed = &notification.embed;
+    let mut lines = vec![format!(
+        "<b>{}</b>",
+        text::escape_html(&notification.message)
+    )];
+
+    if let Some(author) = &embed.author {
+        lines.push(format!("<i>{}</i>", text::escape_html(author)));
+    }
+    if let Some(description) = &embed.description {
+        lines.push(text::escape_html(&truncate(description)));
+    }
+    if let Some(code) = &embed.code_block {
+        lines.push(format!(
+            "<pre><code class=\"language-json\">{}</code></pre>",
+            text::escape_html(&truncate(code))
+        ));
+    }
+
+    if !embed.fields.is_empty() {
+        lines.push("".to_owned());
+        for field in &embed.fields {
+            lines.push(format!(
+                "<b>{}:</b> {}",
+                text::escape_html(&field.name),
+                text::escape_html(&field.value)
+            ));
+        }
+    }
+
+    lines.push("".to_owned());
+    lines.push(format!(
+        "<i>{}</i>",
+        text::escape_html(&text::footer(notification))
+    ));
+
+    lines.join("\n")
+}
+
+fn render_markdown_v2(notification: &ProcessedNotification) -> String {
+    let embed = &notification.embed;
+    let mut lines = vec![format!("*{}*", escape_markdown_v2(&notification.message))];
+
+    if let Some(author) = &embed.author {
+        lines.push(format!("_{}_", escape_markdown_v2(author)));
+    }
+    if let Some(description) = &embed.description {
+        lines.push(escape_markdown_v2(&truncate(description)));
+    }
+    if let Some(code) = &embed.code_block {
+        lines.push(format!(
+            "```json\n{}\n```",
+            truncate(code).replace('\\', "\\\\").replace('`', "\\`")
+        ));
+    }
+
+    if !embed.fields.is_empty() {
+        lines.push("".to_owned());
+        for field in &embed.fields {
+            lines.push(format!(
+                "*{}:* {}",
+                escape_markdown_v2(&field.name),
+                escape_markdown_v2(&field.value)
+            ));
+        }
+    }
+
+    lines.push("".to_owned());
+    lines.push(format!(
+        "_{}_",
+        escape_markdown_v2(&text::footer(notification))
+    ));
+
+    lines.join("\n")
+}
+
+fn escape_markdown_v2(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        if MARKDOWN_V2_CHARS.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+// Messages are limited to 4096 characters, the description is the only part that can get long
+fn truncate(text: &str) -> String {
+    match text.char_indices().nth(MAX_DESCRIPTION_LENGTH) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_markdown_v2() {
+        assert_eq!(
+            escape_markdown_v2("1. *Test* (str. 5-6)!"),
+            "1\\. \\*Test\\* \\(str\\. 5\\-6\\)\\!"
+        );
+        assert_eq!(escape_markdown_v2("a\\b_c"), "a\\\\b\\_c");
+    }
+
+    #[test]
+    fn keeps_plain_text() {
+        assert_eq!(escape_markdown_v2("Zadanie domowe ąę"), "Zadanie domowe ąę");
+    }
+
+    #[test]
+    fn truncates_long_text() {
+        let text = "ą".repeat(MAX_DESCRIPTION_LENGTH + 1);
+        let truncated = truncate(&text);
+
+        assert_eq!(truncated.chars().count(), MAX_DESCRIPTION_LENGTH + 1);
+        assert!(truncated.ends_with('…'));
+        assert_eq!(truncate("krótki"), "krótki");
+    }
+}
@@ -0,0 +1,143 @@
+use crate::notification_types::ProcessedNotification;
+
+pub fn plain_text(notification: &ProcessedNotification) -> String {
+    let embed = &notification.embed;
+    let mut lines = vec![notification.message.clone()];
+
+    if let Some(author) = &embed.author {
+        lines.push(author.clone());
+    }
+    if let Some(description) = &embed.description {
+        lines.push(description.clone());
+    }
+    if let Some(code) = &embed.code_block {
+        lines.push(code.clone());
+    }
+
+    if !embed.fields.is_empty() {
+        lines.push("".to_owned());
+        for field in &embed.fields {
+            lines.push(format!("{}: {}", field.name, field.value));
+        }
+    }
+
+    lines.push("".to_owned());
+    lines.push(footer(notification));
+
+    lines.join("\n")
+}
+
+pub fn html(notification: &ProcessedNotification) -> String {
+    let embed = &notification.embed;
+    let mut html = format!("<b>{}</b>", escape_html(&notification.message));
+
+    if let Some(author) = &embed.author {
+        html.push_str(&format!("<br><i>{}</i>", escape_html(author)));
+    }
+    if let Some(description) = &embed.description {
+        html.push_str(&format!(
+            "<br>{}",
+            escape_html(description).replace('\n', "<br>")
+        ));
+    }
+    if let Some(code) = &embed.code_block {
+        html.push_str(&format!(
+            "<pre><code class=\"language-json\">{}</code></pre>",
+            escape_html(code)
+        ));
+    }
+
+    if !embed.fields.is_empty() {
+        html.push_str("<br>");
+        for field in &embed.fields {
+            html.push_str(&format!(
+                "<br><b>{}:</b> {}",
+                escape_html(&field.name),
+                escape_html(&field.value)
+            ));
+        }
+    }
+
+    html.push_str(&format!(
+        "<br><br><small>{}</small>",
+        escape_html(&footer(notification))
+    ));
+
+    html
+}
+
+pub fn footer(notification: &ProcessedNotification) -> String {
+    format!(
+        "{} / {}",
+        notification.title, notification.embed.notification_type
+    )
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    use crate::test_util;
+
+    #[test]
+    fn renders_plain_text() {
+        let notification = test_util::shared_event(json!({ "topic": "<b>Funkcje</b> & wykresy" }));
+        let text = plain_text(&notification);
+
+        assert!(text.starts_with("Jan Kowalski dodał wydarzenie\nJan Kowalski\n"));
+        assert!(text.contains("\n<b>Funkcje</b> & wykresy\n"));
+        assert!(text.contains("\nPrzedmiot: Matematyka\n"));
+        assert!(text.ends_with("\n\n2a - Nowe wydarzenie / sharedEvent"));
+    }
+
+    #[test]
+    fn escapes_html() {
+        let notification = test_util::shared_event(json!({
+            "topic": "<script>\"x\" & 'y'</script>\nnowa linia",
+            "sharedByName": "<i>Jan</i>"
+        }));
+        let html = html(&notification);
+
+        assert!(html
+            .starts_with("<b>Jan Kowalski dodał wydarzenie</b><br><i>&lt;i&gt;Jan&lt;/i&gt;</i>"));
+        assert!(html.contains(
+            "<br>&lt;script&gt;&quot;x&quot; &amp; &#39;y&#39;&lt;/script&gt;<br>nowa linia"
+        ));
+        assert!(!html.contains("<script>"));
+    }
+
+    #[test]
+    fn renders_fields() {
+        let notification = test_util::shared_event(json!({}));
+        let html = html(&notification);
+
+        assert!(html.contains("<br><b>Grupa:</b> 2a"));
+        assert!(html.contains("<br><b>Przedmiot:</b> Matematyka"));
+        assert!(html.ends_with("<br><br><small>2a - Nowe wydarzenie / sharedEvent</small>"));
+    }
+
+    #[test]
+    fn renders_code_blocks() {
+        let notification = test_util::process(json!({
+            "type": "<unknown>",
+            "title": "Szkolny.eu",
+            "message": "Nieznane powiadomienie"
+        }));
+
+        assert!(plain_text(&notification).contains("\"type\":\"<unknown>\""));
+        assert!(html(&notification).contains(
+            "<pre><code class=\"language-json\">{&quot;message&quot;:&quot;Nieznane powiadomienie&quot;"
+        ));
+        assert!(!html(&notification).contains("<unknown>"));
+    }
+}
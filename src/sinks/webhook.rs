@@ -0,0 +1,171 @@
+use std::{collections::HashMap, error::Error};
+
+use async_trait::async_trait;
+use handlebars::Handlebars;
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use url::Url;
+
+use super::{document::NotificationDocument, DeliveryError, Sink, SinkContext};
+use crate::notification_types::ProcessedNotification;
+
+const TEMPLATE_NAME: &str = "body";
+
+#[derive(Deserialize)]
+pub struct WebhookConfig {
+    url: Url,
+    template: Option<String>,
+    #[serde(default = "default_content_type")]
+    content_type: String,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    secret: Option<String>,
+    #[serde(default = "default_signature_header")]
+    signature_header: String,
+}
+
+fn default_content_type() -> String {
+    "application/json".to_owned()
+}
+
+fn default_signature_header() -> String {
+    "X-Signature-256".to_owned()
+}
+
+#[derive(Serialize)]
+pub struct WebhookMessage {
+    body: String,
+}
+
+pub struct WebhookSink {
+    config: WebhookConfig,
+    templates: Handlebars<'static>,
+    headers: HeaderMap,
+    signature_header: HeaderName,
+}
+
+impl WebhookSink {
+    pub fn new(config: WebhookConfig) -> Self {
+        let mut templates = Handlebars::new();
+        templates.set_strict_mode(true);
+        // Values are escaped as JSON string contents, so `"{{message}}"` is always valid JSON
+        templates.register_escape_fn(|value| {
+            let quoted = serde_json::to_string(value).unwrap();
+            quoted[1..quoted.len() - 1].to_owned()
+        });
+
+        if let Some(template) = &config.template {
+            templates
+                .register_template_string(TEMPLATE_NAME, template)
+                .unwrap();
+        }
+
+        // Invalid headers are config mistakes, so they fail here instead of on every request
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_str(&config.content_type).unwrap(),
+        );
+        for (name, value) in &config.headers {
+            headers.insert(
+                HeaderName::from_bytes(name.as_bytes()).unwrap(),
+                HeaderValue::from_str(value).unwrap(),
+            );
+        }
+        let signature_header = HeaderName::from_bytes(config.signature_header.as_bytes()).unwrap();
+
+        WebhookSink {
+            config,
+            templates,
+            headers,
+            signature_header,
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl Sink for WebhookSink {
+    type Message = WebhookMessage;
+
+    fn render(
+        &self,
+        notification: &ProcessedNotification,
+        _context: &SinkContext<'_>,
+    ) -> Result<Option<WebhookMessage>, Box<dyn Error>> {
+        let document = NotificationDocument::new(notification);
+
+        let body = match &self.config.template {
+            Some(_) => self.templates.render(TEMPLATE_NAME, &document)?,
+            None => serde_json::to_string(&document)?,
+        };
+
+        Ok(Some(WebhookMessage { body }))
+    }
+
+    async fn deliver(
+        &self,
+        message: &WebhookMessage,
+        context: &SinkContext<'_>,
+    ) -> Result<(), DeliveryError> {
+        let mut request = context
+            .client
+            .post(self.config.url.clone())
+            .headers(self.headers.clone());
+
+        if let Some(secret) = &self.config.secret {
+            let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+            mac.update(message.body.as_bytes());
+            request = request.header(
+                &self.signature_header,
+                format!("sha256={}", hex::encode(mac.finalize().into_bytes())),
+            );
+        }
+
+        request
+            .body(message.body.clone())
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn webhook_config(headers: &str) -> WebhookConfig {
+        toml::from_str(&format!(
+            r#"
+            url = "https://example.org/hook"
+            headers = {{ {} }}
+            "#,
+            headers
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn parses_headers() {
+        let sink = WebhookSink::new(webhook_config(r#""X-Token" = "secret""#));
+
+        assert_eq!(sink.headers["x-token"], "secret");
+        assert_eq!(sink.headers[CONTENT_TYPE], "application/json");
+        assert_eq!(sink.signature_header, "x-signature-256");
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_invalid_header_names() {
+        WebhookSink::new(webhook_config(r#""X Token" = "secret""#));
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_invalid_header_values() {
+        WebhookSink::new(webhook_config(r#""X-Token" = "a\nb""#));
+    }
+}
@@ -27,7 +27,7 @@ struct RegisterBrowserResponse {
     data: RegisterBrowserResponseData,
 }
 
-pub async fn register_browser(client: &reqwest::Client, fcm_token: &String) -> (String, String) {
+pub async fn register_browser(client: &reqwest::Client, fcm_token: &str) -> (String, String) {
     let response = client
         .post("https://api.szkolny.eu/webPush")
         .json(&RegisterBrowserBody {
@@ -40,21 +40,21 @@ pub async fn register_browser(client: &reqwest::Client, fcm_token: &String) -> (
 
     let data: RegisterBrowserResponse = response.json().await.unwrap();
 
-    println!("Browser ID: {}", data.data.browser.browser_id);
-    println!("Pair token: {}", data.data.browser.pair_token);
+    log!("Browser ID: {}", data.data.browser.browser_id);
+    log!("Pair token: {}", data.data.browser.pair_token);
 
     (data.data.browser.browser_id, data.data.browser.pair_token)
 }
 
-pub async fn print_registered_devices(client: &reqwest::Client, browser_id: &String) {
+pub async fn print_registered_devices(client: &reqwest::Client, browser_id: &str) {
     let response = client
         .post("https://api.szkolny.eu/webPush")
         .json(&HashMap::from([
             ("action", "listDevices"),
-            ("browserId", &browser_id),
+            ("browserId", browser_id),
         ]))
         .send()
         .await
         .unwrap();
-    println!("Paired devices: {}", response.text().await.unwrap());
+    log!("Paired devices: {}", response.text().await.unwrap());
 }
@@ -1,14 +1,26 @@
+use std::{error::Error, fs, path::Path, time::Duration};
+
 use fcm_push_listener::Registration;
 use futures::StreamExt;
 
-use crate::{db, discord_webhook, fcm_wrapper::FcmMessageStream, LibrusConfig};
+use crate::{
+    db,
+    fcm_wrapper::FcmMessageStream,
+    notification_types::{self, AdminMessagesConfig},
+    sinks::{Output, SinkContext},
+    LibrusConfig,
+};
+
+const TICK_INTERVAL: Duration = Duration::from_secs(60);
 
 pub async fn run(
     registration: Registration,
     database: rusqlite::Connection,
-    webhook_url: String,
+    outputs: &[Box<dyn Output>],
     client: &reqwest::Client,
     librus_config: &LibrusConfig,
+    admin_messages: &AdminMessagesConfig,
+    dry_run: bool,
 ) {
     let notifications = db::get_notifications(&database).unwrap();
 
@@ -16,28 +28,164 @@ pub async fn run(
         .await
         .unwrap();
 
-    println!(" > Listening for messages...");
+    log!(" > Listening for messages...");
+
+    let context = SinkContext {
+        client,
+        database: &database,
+        librus_config,
+    };
+
+    let mut tick_interval = tokio::time::interval(TICK_INTERVAL);
 
-    while let Some(message) = message_stream.next().await {
-        println!("  -> Message JSON: {}", message.payload_json);
+    loop {
+        let message = tokio::select! {
+            message = message_stream.next() => match message {
+                Some(message) => message,
+                None => break,
+            },
+            // Ticks flush digests and publish state, which a dry run must not do
+            _ = tick_interval.tick(), if !dry_run => {
+                for output in outputs {
+                    if let Err(e) = output.tick(&context).await {
+                        eprintln!("  -> {} failed: {}", output.name(), e);
+                    }
+                }
+                continue;
+            }
+        };
 
-        if let Err(_) = discord_webhook::process_message(
-            message.payload_json,
-            webhook_url.clone(),
-            client,
-            librus_config,
+        log!("  -> Message JSON: {}", message.payload_json);
+
+        let persistent_id = message.persistent_id.as_ref().unwrap();
+
+        if process_message(
+            persistent_id,
+            &message.payload_json,
+            outputs,
+            &context,
+            admin_messages,
+            dry_run,
         )
         .await
+        .is_err()
+            || dry_run
         {
             continue;
         }
 
-        db::add_notification(&database, &message.persistent_id.as_ref().unwrap()).unwrap();
+        db::add_notification(&database, persistent_id).unwrap();
+        db::remove_deliveries(&database, persistent_id).unwrap();
     }
 
     eprintln!("FCM message stream ended!");
 }
 
+// Outputs that already got a notification are skipped, so when FCM redelivers a message
+// because another output failed, only the failed ones are retried.
+// In dry run mode every output only renders its message and prints it.
+pub async fn process_message(
+    persistent_id: &str,
+    json: &str,
+    outputs: &[Box<dyn Output>],
+    context: &SinkContext<'_>,
+    admin_messages: &AdminMessagesConfig,
+    dry_run: bool,
+) -> Result<(), Box<dyn Error>> {
+    let notification = match notification_types::process_message(
+        persistent_id,
+        json,
+        context.librus_config,
+        admin_messages,
+    )? {
+        Some(notification) => notification,
+        None => {
+            if dry_run {
+                log!("  -> [dry run] Notification ignored");
+            }
+            return Ok(());
+        }
+    };
+
+    let outputs = outputs
+        .iter()
+        .filter(|output| output.admin() == notification.admin_only);
+
+    if dry_run {
+        for output in outputs {
+            match output.render_json(&notification, context)? {
+                Some(message) => log!(
+                    "  -> [dry run] {}:\n{}",
+                    output.name(),
+                    serde_json::to_string_pretty(&message)?
+                ),
+                None => log!("  -> [dry run] {}: nothing to send", output.name()),
+            }
+        }
+
+        return Ok(());
+    }
+
+    let mut failed = false;
+
+    for output in outputs {
+        if db::is_delivered(context.database, persistent_id, output.name())? {
+            continue;
+        }
+
+        match output.send(&notification, context).await {
+            Ok(()) => db::add_delivery(context.database, persistent_id, output.name())?,
+            Err(e) => {
+                eprintln!("  -> Giving up on {} for now: {}", output.name(), e);
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        return Err("some outputs failed".into());
+    }
+
+    // Failed messages are processed again, the IDs are only counted once they went through
+    for seen_id in &notification.seen_ids {
+        db::add_seen_id(
+            context.database,
+            &seen_id.profile,
+            seen_id.kind.as_str(),
+            &seen_id.id,
+            &seen_id.topic,
+            &seen_id.shared_by,
+        )?;
+    }
+
+    Ok(())
+}
+
+// Accepts either a whole FCM message (`{"data": {...}}`) or just the Szkolny payload
+pub async fn render_payload_file(
+    path: &Path,
+    outputs: &[Box<dyn Output>],
+    context: &SinkContext<'_>,
+    admin_messages: &AdminMessagesConfig,
+) -> Result<(), Box<dyn Error>> {
+    let payload: serde_json::Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+    let fcm_message = if payload.get("data").is_some() {
+        payload
+    } else {
+        serde_json::json!({ "data": payload })
+    };
+
+    process_message(
+        &path.display().to_string(),
+        &fcm_message.to_string(),
+        outputs,
+        context,
+        admin_messages,
+        true,
+    )
+    .await
+}
+
 pub async fn register(sender_id: &str) -> Result<Registration, fcm_push_listener::Error> {
     let registration = fcm_push_listener::register(sender_id).await?;
 
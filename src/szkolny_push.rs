@@ -0,0 +1,252 @@
+use serde::{de::DeserializeOwned, Deserialize, Deserializer};
+
+// Every web push sent by Szkolny.eu is a flat map of strings, with the `type` key telling what
+// it is. Nested objects (events, notes, updates) are sent as JSON encoded strings.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SzkolnyPush {
+    // The team code is also sent as `shareTeamCode`, but the event already contains it
+    SharedEvent {
+        #[serde(deserialize_with = "json_string")]
+        event: SharedEvent,
+    },
+    #[serde(rename_all = "camelCase")]
+    UnsharedEvent {
+        unshare_team_code: String,
+        event_id: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    SharedNote {
+        share_team_code: String,
+        #[serde(deserialize_with = "json_string")]
+        note: SharedNote,
+    },
+    #[serde(rename_all = "camelCase")]
+    UnsharedNote {
+        unshare_team_code: String,
+        note_id: String,
+    },
+    #[serde(alias = "unpairedBrowser")]
+    ServerMessage(PushMessage),
+    AppUpdate {
+        #[serde(deserialize_with = "json_string")]
+        update: AppUpdate,
+    },
+    FeedbackMessage(PushMessage),
+    UserActionRequired(PushMessage),
+    RegisterAvailability(PushMessage),
+    // Keep-alive and sync requests only carry the type
+    Ping,
+    SyncNotify,
+    #[serde(other)]
+    Unknown,
+}
+
+// Which kind of push a notification came from, keep-alive and sync pushes never make one
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PushKind {
+    SharedEvent,
+    UnsharedEvent,
+    SharedNote,
+    UnsharedNote,
+    ServerMessage,
+    AppUpdate,
+    FeedbackMessage,
+    UserActionRequired,
+    RegisterAvailability,
+    Unknown,
+}
+
+impl PushKind {
+    // The `type` of the push, unknown pushes keep their own in the notification
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            PushKind::SharedEvent => "sharedEvent",
+            PushKind::UnsharedEvent => "unsharedEvent",
+            PushKind::SharedNote => "sharedNote",
+            PushKind::UnsharedNote => "unsharedNote",
+            PushKind::ServerMessage => "serverMessage",
+            PushKind::AppUpdate => "appUpdate",
+            PushKind::FeedbackMessage => "feedbackMessage",
+            PushKind::UserActionRequired => "userActionRequired",
+            PushKind::RegisterAvailability => "registerAvailability",
+            PushKind::Unknown => "unknown",
+        }
+    }
+}
+
+// Text of messages sent by Szkolny.eu itself rather than by other users. The title is common
+// to every push and read separately.
+#[derive(Deserialize)]
+pub struct PushMessage {
+    #[serde(default)]
+    pub message: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedEvent {
+    pub id: u64,
+    #[serde(rename = "type")]
+    pub event_type: i32,
+    pub subject_id: i32,
+    pub teacher_id: i32,
+    pub team_code: String,
+    pub topic: String,
+    pub event_date: u64,
+    pub start_time: Option<u64>,
+    pub shared_by_name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedNote {
+    pub id: u64,
+    pub owner_type: Option<String>,
+    pub owner_id: Option<u64>,
+    pub topic: Option<String>,
+    pub body: String,
+    pub color: Option<i64>,
+    pub shared_by_name: String,
+    pub added_date: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppUpdate {
+    pub version_code: Option<u32>,
+    pub version_name: String,
+    pub release_date: Option<String>,
+    pub release_notes: Option<String>,
+    pub release_type: Option<String>,
+    pub download_url: Option<String>,
+    #[serde(default)]
+    pub update_mandatory: bool,
+}
+
+fn json_string<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    let json = String::deserialize(deserializer)?;
+    serde_json::from_str(&json).map_err(serde::de::Error::custom)
+}
+
+impl SzkolnyPush {
+    // Payloads of a known type that can't be parsed are treated like unknown ones, so that they
+    // still get forwarded as raw JSON instead of being lost
+    pub fn parse(payload: &serde_json::Value) -> SzkolnyPush {
+        match SzkolnyPush::deserialize(payload) {
+            Ok(push) => push,
+            Err(e) => {
+                eprintln!("  -> Failed to parse {} push: {}", payload["type"], e);
+                SzkolnyPush::Unknown
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    fn shared_event_payload() -> serde_json::Value {
+        json!({
+            "type": "sharedEvent",
+            "title": "2a - Nowe wydarzenie",
+            "message": "Jan Kowalski dodał wydarzenie",
+            "shareTeamCode": "PL_LIBRUS_12345:2a",
+            "event": json!({
+                "id": 1697012345678u64,
+                "teamCode": "PL_LIBRUS_12345:2a",
+                "type": 1,
+                "topic": "Funkcje kwadratowe",
+                "subjectId": 123,
+                "teacherId": 456,
+                "eventDate": 20231012,
+                "startTime": 80000,
+                "color": null,
+                "sharedBy": "abc",
+                "sharedByName": "Jan Kowalski",
+                "addedDate": 1697012345678u64
+            })
+            .to_string()
+        })
+    }
+
+    #[test]
+    fn parses_shared_event() {
+        let push = SzkolnyPush::parse(&shared_event_payload());
+
+        let SzkolnyPush::SharedEvent { event } = push else {
+            panic!("not parsed as a shared event");
+        };
+        assert_eq!(event.id, 1697012345678);
+        assert_eq!(event.event_type, 1);
+        assert_eq!(event.subject_id, 123);
+        assert_eq!(event.teacher_id, 456);
+        assert_eq!(event.team_code, "PL_LIBRUS_12345:2a");
+        assert_eq!(event.start_time, Some(80000));
+        assert_eq!(event.shared_by_name, "Jan Kowalski");
+    }
+
+    #[test]
+    fn parses_unshared_event() {
+        let push = SzkolnyPush::parse(&json!({
+            "type": "unsharedEvent",
+            "title": "2a - Usunięto wydarzenie",
+            "message": "Jan Kowalski usunął wydarzenie",
+            "unshareTeamCode": "PL_LIBRUS_12345:2a",
+            "eventId": "1697012345678"
+        }));
+
+        let SzkolnyPush::UnsharedEvent {
+            unshare_team_code,
+            event_id,
+        } = push
+        else {
+            panic!("not parsed as an unshared event");
+        };
+        assert_eq!(unshare_team_code, "PL_LIBRUS_12345:2a");
+        assert_eq!(event_id, "1697012345678");
+    }
+
+    #[test]
+    fn parses_server_message() {
+        let push = SzkolnyPush::parse(&json!({
+            "type": "unpairedBrowser",
+            "title": "Rozłączono",
+            "message": "Ta przeglądarka została odłączona"
+        }));
+
+        let SzkolnyPush::ServerMessage(message) = push else {
+            panic!("not parsed as a server message");
+        };
+        assert_eq!(message.message, "Ta przeglądarka została odłączona");
+    }
+
+    #[test]
+    fn parses_sync_notify() {
+        let push = SzkolnyPush::parse(&json!({ "type": "syncNotify" }));
+
+        assert!(matches!(push, SzkolnyPush::SyncNotify));
+    }
+
+    #[test]
+    fn unknown_type_is_unknown() {
+        let push = SzkolnyPush::parse(&json!({ "type": "somethingNew", "title": "a" }));
+
+        assert!(matches!(push, SzkolnyPush::Unknown));
+    }
+
+    #[test]
+    fn broken_event_is_unknown() {
+        let mut payload = shared_event_payload();
+        payload["event"] = json!("{not json");
+
+        assert!(matches!(SzkolnyPush::parse(&payload), SzkolnyPush::Unknown));
+    }
+}
@@ -0,0 +1,57 @@
+use serde_json::{json, Value};
+
+use crate::{
+    notification_types::{self, AdminMessagesConfig, ProcessedNotification},
+    LibrusConfig,
+};
+
+pub fn librus_config() -> LibrusConfig {
+    toml::from_str(
+        r#"
+        teams = ["2a"]
+        subjects = { "123" = "Matematyka" }
+        teachers = { "456" = "Jan Nowak" }
+        "#,
+    )
+    .unwrap()
+}
+
+// Processes a Szkolny payload the same way as one that came through FCM
+pub fn process(payload: Value) -> ProcessedNotification {
+    notification_types::process_message(
+        "1",
+        &json!({ "data": payload }).to_string(),
+        &librus_config(),
+        &AdminMessagesConfig::default(),
+    )
+    .unwrap()
+    .unwrap()
+}
+
+// A shared event where the fields in `event` replace the default ones
+pub fn shared_event(event: Value) -> ProcessedNotification {
+    let mut shared_event = json!({
+        "id": 1,
+        "teamCode": "2a",
+        "type": 1,
+        "topic": "Funkcje kwadratowe",
+        "subjectId": 123,
+        "teacherId": 456,
+        "eventDate": 20231012,
+        "startTime": 80000,
+        "color": null,
+        "sharedBy": "abc",
+        "sharedByName": "Jan Kowalski",
+        "addedDate": 1697012345678u64
+    });
+    for (key, value) in event.as_object().unwrap() {
+        shared_event[key] = value.clone();
+    }
+
+    process(json!({
+        "type": "sharedEvent",
+        "title": "2a - Nowe wydarzenie",
+        "message": "Jan Kowalski dodał wydarzenie",
+        "event": shared_event.to_string()
+    }))
+}